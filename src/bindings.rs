@@ -0,0 +1,402 @@
+use macroquad::prelude::*;
+
+/// Bindings file, read at startup and written back by the controls screen.
+/// Lives next to the `assets/` folder (paths are relative to the working dir).
+pub const BINDINGS_PATH: &str = "bindings.toml";
+
+/// Logical actions the game reads instead of raw key codes.
+///
+/// Escape is intentionally not bindable: it always backs out of screens and
+/// cancels a pending rebind, so a bad binding can never lock you out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Crouch,
+    Sprint,
+    Jump,
+    Dash,
+    ModeIdle,
+    ModeWalk,
+    ModeRun,
+    Confirm,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Crouch,
+        Action::Sprint,
+        Action::Jump,
+        Action::Dash,
+        Action::ModeIdle,
+        Action::ModeWalk,
+        Action::ModeRun,
+        Action::Confirm,
+    ];
+
+    /// Key used in the bindings file.
+    fn config_name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Crouch => "crouch",
+            Action::Sprint => "sprint",
+            Action::Jump => "jump",
+            Action::Dash => "dash",
+            Action::ModeIdle => "mode_idle",
+            Action::ModeWalk => "mode_walk",
+            Action::ModeRun => "mode_run",
+            Action::Confirm => "confirm",
+        }
+    }
+
+    /// Human-readable name for the controls screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Face left",
+            Action::MoveRight => "Face right",
+            Action::Crouch => "Crouch / slide",
+            Action::Sprint => "Sprint",
+            Action::Jump => "Jump",
+            Action::Dash => "Dash",
+            Action::ModeIdle => "Mode: idle",
+            Action::ModeWalk => "Mode: walk",
+            Action::ModeRun => "Mode: run",
+            Action::Confirm => "Confirm",
+        }
+    }
+
+    fn index(self) -> usize {
+        Action::ALL.iter().position(|a| *a == self).unwrap()
+    }
+}
+
+/// Keys that can be named in the bindings file (matched by their `Debug` name).
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpEnter,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+/// Why `key` can't be bound to `action`: keys the game handles itself.
+fn reserved(action: Action, key: KeyCode) -> Option<&'static str> {
+    match key {
+        KeyCode::Escape => Some("Escape is reserved for back/cancel"),
+        KeyCode::F9 => Some("F9 is reserved for recording"),
+        KeyCode::F11 => Some("F11 is reserved for fullscreen"),
+        // the menus always move with Up/Down, so they would confirm and move at once
+        KeyCode::Up | KeyCode::Down if action == Action::Confirm => {
+            Some("Up/Down move the menu cursor and can't be Confirm")
+        }
+        // main menu shortcuts: Confirm there would also start the demo
+        KeyCode::C | KeyCode::S if action == Action::Confirm => {
+            Some("C and S open controls/settings from the menu and can't be Confirm")
+        }
+        _ => None,
+    }
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|k| key_name(*k).eq_ignore_ascii_case(name))
+}
+
+/// Action -> key table. At most one action per key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    keys: [KeyCode; Action::ALL.len()],
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: [
+                KeyCode::Left,
+                KeyCode::Right,
+                KeyCode::Down,
+                KeyCode::LeftShift,
+                KeyCode::J,
+                KeyCode::D,
+                KeyCode::I,
+                KeyCode::W,
+                KeyCode::R,
+                KeyCode::Enter,
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    /// Load from `BINDINGS_PATH`, falling back to defaults when missing.
+    pub async fn load() -> Self {
+        match load_string(BINDINGS_PATH).await {
+            Ok(text) => Self::parse(&text),
+            Err(_) => {
+                info!("no {} found, using default bindings", BINDINGS_PATH);
+                Self::default()
+            }
+        }
+    }
+
+    /// Parse `action = "Key"` lines on top of the defaults. Unknown actions and
+    /// keys are skipped with a warning; if the result binds one key to two
+    /// actions the whole file is rejected in favour of the defaults.
+    pub fn parse(text: &str) -> Self {
+        let mut bindings = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                warn!("{}: ignoring malformed line {:?}", BINDINGS_PATH, line);
+                continue;
+            };
+            let name = name.trim();
+            let value = value.trim().trim_matches('"');
+            let Some(action) = Action::ALL
+                .iter()
+                .copied()
+                .find(|a| a.config_name() == name)
+            else {
+                warn!("{}: unknown action {:?}", BINDINGS_PATH, name);
+                continue;
+            };
+            let Some(key) = key_from_name(value) else {
+                warn!("{}: unknown key {:?} for {}", BINDINGS_PATH, value, name);
+                continue;
+            };
+            if let Some(why) = reserved(action, key) {
+                warn!(
+                    "{}: {}; keeping the default for {}",
+                    BINDINGS_PATH, why, name
+                );
+                continue;
+            }
+            bindings.keys[action.index()] = key;
+        }
+        if let Some(msg) = bindings.first_conflict() {
+            warn!("{}: {}; using default bindings", BINDINGS_PATH, msg);
+            return Self::default();
+        }
+        bindings
+    }
+
+    fn first_conflict(&self) -> Option<String> {
+        for (i, a) in Action::ALL.iter().enumerate() {
+            for b in &Action::ALL[i + 1..] {
+                if self.key(*a) == self.key(*b) {
+                    return Some(format!(
+                        "{} is bound to both {} and {}",
+                        key_name(self.key(*a)),
+                        a.label(),
+                        b.label()
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let mut out = String::from("# Key bindings (action = \"KeyName\")\n");
+        for action in Action::ALL {
+            out.push_str(&format!(
+                "{} = \"{}\"\n",
+                action.config_name(),
+                key_name(self.key(action))
+            ));
+        }
+        out
+    }
+
+    /// Write back to `BINDINGS_PATH`. On the web build there is no writable
+    /// file system, so changes only last for the session.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> Result<(), String> {
+        std::fs::write(BINDINGS_PATH, self.to_config_string()).map_err(|e| e.to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) -> Result<(), String> {
+        Err("saving bindings is not supported on the web build".to_string())
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys[action.index()]
+    }
+
+    /// Bind `action` to `key`, rejecting reserved keys, keys the bindings
+    /// file could not name, and keys already used by another action.
+    pub fn set(&mut self, action: Action, key: KeyCode) -> Result<(), String> {
+        if let Some(why) = reserved(action, key) {
+            return Err(why.to_string());
+        }
+        if !BINDABLE_KEYS.contains(&key) {
+            return Err(format!("{} can't be bound", key_name(key)));
+        }
+        if let Some(other) = Action::ALL
+            .iter()
+            .copied()
+            .find(|a| *a != action && self.key(*a) == key)
+        {
+            return Err(format!(
+                "{} is already bound to {}",
+                key_name(key),
+                other.label()
+            ));
+        }
+        self.keys[action.index()] = key;
+        Ok(())
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        is_key_down(self.key(action))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        is_key_pressed(self.key(action))
+    }
+
+    pub fn released(&self, action: Action) -> bool {
+        is_key_released(self.key(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_names_keep_the_default() {
+        let bindings = Bindings::parse(
+            "jump = \"K\"\nfly = \"X\"\ndash = \"NotAKey\"\nthis line is malformed\n",
+        );
+        assert_eq!(bindings.key(Action::Jump), KeyCode::K);
+        assert_eq!(
+            bindings.key(Action::Dash),
+            Bindings::default().key(Action::Dash)
+        );
+    }
+
+    #[test]
+    fn reserved_keys_in_the_file_are_skipped() {
+        let bindings = Bindings::parse(
+            "jump = \"F9\"\nconfirm = \"Up\"\nconfirm = \"C\"\nsprint = \"Escape\"\n",
+        );
+        assert_eq!(bindings, Bindings::default());
+    }
+
+    #[test]
+    fn shared_key_rejects_the_whole_file() {
+        let bindings = Bindings::parse("jump = \"K\"\ndash = \"Space\"\nsprint = \"Space\"\n");
+        assert_eq!(bindings, Bindings::default());
+    }
+
+    #[test]
+    fn set_refuses_bad_keys() {
+        let mut bindings = Bindings::default();
+        let taken = bindings.key(Action::Dash);
+        assert!(bindings.set(Action::Jump, taken).is_err());
+        assert!(bindings.set(Action::Jump, KeyCode::Escape).is_err());
+        assert!(bindings.set(Action::Jump, KeyCode::F11).is_err());
+        assert!(bindings.set(Action::Confirm, KeyCode::Down).is_err());
+        assert!(bindings.set(Action::Confirm, KeyCode::C).is_err());
+        assert!(bindings.set(Action::Jump, KeyCode::F1).is_err());
+        assert_eq!(bindings, Bindings::default());
+
+        assert!(bindings.set(Action::Jump, KeyCode::Space).is_ok());
+        assert_eq!(bindings.key(Action::Jump), KeyCode::Space);
+    }
+
+    #[test]
+    fn config_string_round_trips() {
+        let mut bindings = Bindings::default();
+        bindings.set(Action::Jump, KeyCode::Space).unwrap();
+        bindings.set(Action::Confirm, KeyCode::Tab).unwrap();
+        assert_eq!(Bindings::parse(&bindings.to_config_string()), bindings);
+    }
+}
@@ -0,0 +1,117 @@
+use macroquad::prelude::*;
+
+use crate::MoveState;
+use crate::bindings::{Action, Bindings};
+use crate::touch::{Button, TouchFrame};
+
+/// Everything the game reads from the player in one frame.
+///
+/// Gathered once at the top of the main loop by `poll`; game logic only ever
+/// looks at this struct, never at macroquad's key state, so it can be driven
+/// by scripted or recorded input (`InputState { jump_pressed: true, ..Default::default() }`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputState {
+    // --- held ---
+    pub left: bool,
+    pub right: bool,
+    pub down: bool,
+    pub sprint: bool,
+    pub jump_held: bool,
+
+    // --- gameplay edges ---
+    pub jump_pressed: bool,
+    pub jump_released: bool,
+    pub dash_pressed: bool,
+    pub mode_pressed: Option<MoveState>,
+    /// Touch MODE button: advance Idle -> Walk -> Run -> Idle.
+    pub mode_cycle_pressed: bool,
+
+    // --- menu edges ---
+    pub confirm_pressed: bool,
+    /// Escape: back out of the current screen (never rebindable).
+    pub back_pressed: bool,
+    pub menu_up: bool,
+    pub menu_down: bool,
+    /// Main menu shortcut to the controls screen.
+    pub controls_pressed: bool,
+    /// Main menu shortcut to the settings screen.
+    pub settings_pressed: bool,
+    /// Raw key pressed this frame, for the rebind prompt.
+    pub any_key: Option<KeyCode>,
+    /// F9 in the demo: start/stop recording a replay.
+    pub record_pressed: bool,
+    /// F11 or Alt+Enter, on every screen.
+    pub fullscreen_pressed: bool,
+}
+
+impl InputState {
+    /// Read keyboard (through the bindings) and on-screen touch buttons.
+    pub fn poll(bindings: &Bindings, touch: &TouchFrame) -> Self {
+        let alt = is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt);
+        let alt_enter = alt && is_key_pressed(KeyCode::Enter);
+        let fullscreen_pressed = alt_enter || is_key_pressed(KeyCode::F11);
+        let jump_held = bindings.down(Action::Jump) || touch.held.get(Button::Jump);
+        let mode_pressed = [
+            (Action::ModeIdle, MoveState::Idle),
+            (Action::ModeWalk, MoveState::Walk),
+            (Action::ModeRun, MoveState::Run),
+        ]
+        .into_iter()
+        .rev()
+        .find(|(action, _)| bindings.pressed(*action))
+        .map(|(_, mode)| mode);
+
+        Self {
+            left: bindings.down(Action::MoveLeft) || touch.held.get(Button::Left),
+            right: bindings.down(Action::MoveRight) || touch.held.get(Button::Right),
+            down: bindings.down(Action::Crouch) || touch.held.get(Button::Crouch),
+            sprint: bindings.down(Action::Sprint),
+            jump_held,
+
+            jump_pressed: bindings.pressed(Action::Jump) || touch.pressed.get(Button::Jump),
+            // released only once neither source still holds it
+            jump_released: (bindings.released(Action::Jump) || touch.released.get(Button::Jump))
+                && !jump_held,
+            dash_pressed: bindings.pressed(Action::Dash) || touch.pressed.get(Button::Dash),
+            mode_pressed,
+            mode_cycle_pressed: touch.pressed.get(Button::Mode),
+
+            // Alt+Enter is the fullscreen toggle, not Confirm (Enter by default)
+            confirm_pressed: !alt_enter && (bindings.pressed(Action::Confirm) || touch.tapped),
+            back_pressed: is_key_pressed(KeyCode::Escape),
+            menu_up: is_key_pressed(KeyCode::Up),
+            menu_down: is_key_pressed(KeyCode::Down),
+            controls_pressed: is_key_pressed(KeyCode::C),
+            settings_pressed: is_key_pressed(KeyCode::S),
+            any_key: get_last_key_pressed().filter(|_| !fullscreen_pressed),
+            record_pressed: is_key_pressed(KeyCode::F9),
+            fullscreen_pressed,
+        }
+    }
+
+    /// Fold this frame into the input pending for the next fixed step: held
+    /// state is replaced, edges are OR-ed in until `clear_edges`. This way an
+    /// edge is neither lost (frame with no step) nor repeated (several steps).
+    pub fn accumulate(&mut self, frame: &InputState) {
+        self.left = frame.left;
+        self.right = frame.right;
+        self.down = frame.down;
+        self.sprint = frame.sprint;
+        self.jump_held = frame.jump_held;
+
+        self.jump_pressed |= frame.jump_pressed;
+        self.jump_released |= frame.jump_released;
+        self.dash_pressed |= frame.dash_pressed;
+        self.mode_pressed = frame.mode_pressed.or(self.mode_pressed);
+        self.mode_cycle_pressed |= frame.mode_cycle_pressed;
+    }
+
+    /// Drop gameplay edges once a fixed step has seen them.
+    pub fn clear_edges(&mut self) {
+        self.jump_pressed = false;
+        self.jump_released = false;
+        self.dash_pressed = false;
+        self.mode_pressed = None;
+        self.mode_cycle_pressed = false;
+    }
+}
@@ -1,9 +1,143 @@
 use macroquad::prelude::*;
 
+mod bindings;
+mod input;
+mod particles;
+mod postfx;
+mod replay;
+mod settings;
+mod touch;
+
+use bindings::{Action, Bindings, key_name};
+use input::InputState;
+use particles::Particles;
+use postfx::PostFx;
+use replay::{Playback, RECORDING_PATH, Recording};
+use settings::{FullscreenWatch, Setting, Settings};
+use touch::TouchControls;
+
+// === Jump tuning ============================================================
+const GRAVITY: f32 = 2200.0;
+/// Terminal fall speed (px/s); vel.y is clamped to this after gravity.
+const MAX_FALL_SPEED: f32 = 1200.0;
+const JUMP_SPEED: f32 = 900.0;
+/// Grace period after leaving the ground during which a jump still fires.
+const COYOTE_TIME: f32 = 0.10;
+/// How early J may be pressed before landing and still trigger a jump.
+const JUMP_BUFFER_TIME: f32 = 0.12;
+/// Extra jumps available while airborne; recharged on landing.
+const MAX_AIR_JUMPS: u32 = 1;
+/// Air jumps are slightly weaker than the grounded jump.
+const AIR_JUMP_SPEED: f32 = 0.85 * JUMP_SPEED;
+
+// === Horizontal movement tuning ============================================
+const WALK_SPEED: f32 = 250.0;
+const RUN_SPEED: f32 = 450.0;
+/// px/s² toward the target speed while grounded.
+const GROUND_ACCEL: f32 = 2400.0;
+/// px/s² toward zero while grounded in Idle mode.
+const GROUND_FRICTION: f32 = 3000.0;
+/// px/s² toward the target speed while airborne (limited air control).
+const AIR_ACCEL: f32 = 1200.0;
+/// Extra accel factor when the target opposes current velocity (snappy turns).
+const TURN_ACCEL_MULT: f32 = 2.0;
+/// Holding Shift on the ground scales the target speed (and the jump it carries into).
+const SPRINT_MULTIPLIER: f32 = 1.5;
+const MAX_GROUND_SPEED: f32 = RUN_SPEED * SPRINT_MULTIPLIER;
+
+// === Dash tuning ============================================================
+const DASH_SPEED: f32 = 1200.0;
+/// Dash length in seconds; gravity is suspended for its duration.
+const DASH_TIME: f32 = 0.15;
+/// Time from dash start until the next dash is allowed.
+const DASH_COOLDOWN: f32 = 1.0;
+
+// === Simulation timing =====================================================
+/// Physics/animation run in fixed steps of this length; tuning stays per-second.
+const FIXED_DT: f32 = 1.0 / 120.0;
+/// Frame time is clamped to this, so a hitch (window drag, resumed browser tab)
+/// queues at most a few fixed steps instead of one huge catch-up burst.
+const MAX_FRAME_DT: f32 = 1.0 / 20.0;
+
+// === World / view ===========================================================
+/// The world is a fixed 1280x720 units regardless of window size.
+const WORLD_W: f32 = 1280.0;
+const WORLD_H: f32 = 720.0;
+/// Internal render target the world is drawn into, then scaled up to the
+/// window. Half the world size, so one sprite pixel (6 world units) is
+/// exactly 3 target pixels and stays crisp under Nearest filtering.
+const VIEW_W: u32 = 640;
+const VIEW_H: u32 = 360;
+
+// === Crouch / slide tuning ==================================================
+/// Hitbox (and drawn sprite) height while crouched, relative to standing.
+const CROUCH_HEIGHT_FRAC: f32 = 0.6;
+/// Crouching above this ground speed starts a slide instead.
+const SLIDE_MIN_SPEED: f32 = 300.0;
+const SLIDE_TIME: f32 = 0.45;
+/// px/s² of decel during a slide (much lower than GROUND_FRICTION).
+const SLIDE_FRICTION: f32 = 500.0;
+
+// === Menus ==================================================================
+/// Seconds without input on the main menu before the attract demo starts.
+const ATTRACT_DELAY: f32 = 10.0;
+/// Canned run played behind the menu (a recording, see `replay`).
+const ATTRACT_DEMO_PATH: &str = "assets/demo.replay";
+/// Rows of the pause menu, in order. The web build can't close its tab, so
+/// it has no "Quit game".
+const PAUSE_OPTIONS: &[PauseOption] = if cfg!(target_arch = "wasm32") {
+    &[
+        PauseOption::Resume,
+        PauseOption::Restart,
+        PauseOption::Controls,
+        PauseOption::Settings,
+        PauseOption::QuitToMenu,
+    ]
+} else {
+    &[
+        PauseOption::Resume,
+        PauseOption::Restart,
+        PauseOption::Controls,
+        PauseOption::Settings,
+        PauseOption::QuitToMenu,
+        PauseOption::QuitGame,
+    ]
+};
+/// Rows of the quit prompt; the default (0) is the safe answer.
+const QUIT_OPTIONS: [&str; 2] = ["No", "Yes"];
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum GameState {
     MainMenu,
     Demo,
+    Paused,
+    Controls,
+    Settings,
+    /// "Quit the game?" prompt, over whichever screen asked for it.
+    ConfirmQuit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PauseOption {
+    Resume,
+    Restart,
+    Controls,
+    Settings,
+    QuitToMenu,
+    QuitGame,
+}
+
+impl PauseOption {
+    fn label(self) -> &'static str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::Restart => "Restart",
+            PauseOption::Controls => "Controls",
+            PauseOption::Settings => "Settings",
+            PauseOption::QuitToMenu => "Quit to menu",
+            PauseOption::QuitGame => "Quit game",
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -13,6 +147,8 @@ enum MoveState {
     Run,
     Jump,
     Dash,
+    Crouch,
+    Slide,
 }
 
 /// Jump phase model (semantic, not time-based).
@@ -34,6 +170,8 @@ enum AnimId {
     JumpApex,
     JumpFall,
     JumpLand,
+    Crouch,
+    Slide,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -42,9 +180,43 @@ enum AnimMode {
     Once,
 }
 
+/// How a spritesheet gets drawn. Chosen once at load time: if the PNG is
+/// missing (e.g. a checkout without assets) we draw colored blocks instead of
+/// panicking, so the demo stays usable.
+enum SpriteRenderer {
+    Textured(Texture2D),
+    Fallback(Color),
+}
+
+async fn load_sprite_renderer(path: &str, fallback: Color) -> SpriteRenderer {
+    let loaded = load_texture(path).await;
+    if let Ok(tex) = &loaded {
+        tex.set_filter(FilterMode::Nearest);
+    }
+    choose_renderer(path, loaded, fallback)
+}
+
+/// The load-time choice on its own, so it can be checked without a window.
+fn choose_renderer<E: std::fmt::Debug>(
+    path: &str,
+    loaded: Result<Texture2D, E>,
+    fallback: Color,
+) -> SpriteRenderer {
+    match loaded {
+        Ok(tex) => SpriteRenderer::Textured(tex),
+        Err(err) => {
+            warn!(
+                "failed to load {}: {:?}; using fallback renderer",
+                path, err
+            );
+            SpriteRenderer::Fallback(fallback)
+        }
+    }
+}
+
 /// Simple frame-based animation from a spritesheet.
 struct SpriteAnim {
-    texture: Texture2D,
+    renderer: SpriteRenderer,
     frames: Vec<Rect>,
     durations: Vec<f32>,
     t: f32,
@@ -52,13 +224,18 @@ struct SpriteAnim {
 }
 
 impl SpriteAnim {
-    fn new(texture: Texture2D, frames: Vec<Rect>, durations: Vec<f32>, mode: AnimMode) -> Self {
+    fn new(
+        renderer: SpriteRenderer,
+        frames: Vec<Rect>,
+        durations: Vec<f32>,
+        mode: AnimMode,
+    ) -> Self {
         assert!(
             !frames.is_empty() && frames.len() == durations.len(),
             "frames and durations must be same non-zero length"
         );
         Self {
-            texture,
+            renderer,
             frames,
             durations,
             t: 0.0,
@@ -99,20 +276,43 @@ impl SpriteAnim {
         self.frames.len() - 1
     }
 
-    fn draw(&self, pos: Vec2, scale: f32, flip_x: bool) {
+    fn is_fallback(&self) -> bool {
+        matches!(self.renderer, SpriteRenderer::Fallback(_))
+    }
+
+    fn draw(&self, pos: Vec2, scale: Vec2, flip_x: bool) {
         let src = self.frames[self.frame_index()];
-        draw_texture_ex(
-            &self.texture,
-            pos.x,
-            pos.y,
-            WHITE,
-            DrawTextureParams {
-                source: Some(src),
-                dest_size: Some(vec2(src.w * scale, src.h * scale)),
-                flip_x,
-                ..Default::default()
-            },
-        );
+        let (w, h) = (src.w * scale.x, src.h * scale.y);
+        match &self.renderer {
+            SpriteRenderer::Textured(texture) => {
+                draw_texture_ex(
+                    texture,
+                    pos.x,
+                    pos.y,
+                    WHITE,
+                    DrawTextureParams {
+                        source: Some(src),
+                        dest_size: Some(vec2(w, h)),
+                        flip_x,
+                        ..Default::default()
+                    },
+                );
+            }
+            SpriteRenderer::Fallback(color) => {
+                draw_rectangle(pos.x, pos.y, w, h, *color);
+                // Facing notch + frame ticker so direction and timing stay readable.
+                let notch_x = if flip_x { pos.x } else { pos.x + w * 0.8 };
+                draw_rectangle(notch_x, pos.y + h * 0.2, w * 0.2, h * 0.2, WHITE);
+                let tick_w = w / self.frames.len() as f32;
+                draw_rectangle(
+                    pos.x + tick_w * self.frame_index() as f32,
+                    pos.y + h - 4.0,
+                    tick_w,
+                    4.0,
+                    BLACK,
+                );
+            }
+        }
     }
 }
 
@@ -126,6 +326,100 @@ struct AnimSet {
     jump_apex: SpriteAnim,
     jump_fall: SpriteAnim,
     jump_land: SpriteAnim,
+    crouch: SpriteAnim,
+    slide: SpriteAnim,
+}
+
+impl AnimSet {
+    /// Frame layout and timings for every animation, given one sheet each in
+    /// field order. Each sheet is 1 row, frames are 32x32.
+    fn new(sheets: [SpriteRenderer; 11]) -> Self {
+        let [
+            idle_sheet,
+            walk_sheet,
+            run_sheet,
+            dash_sheet,
+            jt_sheet,
+            jr_sheet,
+            ja_sheet,
+            jf_sheet,
+            jl_sheet,
+            crouch_sheet,
+            slide_sheet,
+        ] = sheets;
+        Self {
+            idle: SpriteAnim::new(
+                idle_sheet,
+                sheet_frames(5, 32.0, 32.0),
+                vec![0.20; 5],
+                AnimMode::Loop,
+            ),
+            walk: SpriteAnim::new(
+                walk_sheet,
+                sheet_frames(4, 32.0, 32.0),
+                vec![0.12; 4],
+                AnimMode::Loop,
+            ),
+            run: SpriteAnim::new(
+                run_sheet,
+                sheet_frames(4, 32.0, 32.0),
+                vec![0.08; 4],
+                AnimMode::Loop,
+            ),
+            dash: SpriteAnim::new(
+                dash_sheet,
+                sheet_frames(3, 32.0, 32.0),
+                vec![0.06; 3],
+                AnimMode::Loop,
+            ),
+
+            // Jump phases: takeoff + landing are "Once". Air poses can be 1-frame "Loop" (held).
+            jump_takeoff: SpriteAnim::new(
+                jt_sheet,
+                sheet_frames(3, 32.0, 32.0),
+                vec![0.06; 3],
+                AnimMode::Once,
+            ),
+            jump_rise: SpriteAnim::new(
+                jr_sheet,
+                sheet_frames(1, 32.0, 32.0),
+                vec![1.0; 1],
+                AnimMode::Loop,
+            ),
+            jump_apex: SpriteAnim::new(
+                ja_sheet,
+                sheet_frames(1, 32.0, 32.0),
+                vec![1.0; 1],
+                AnimMode::Loop,
+            ),
+            jump_fall: SpriteAnim::new(
+                jf_sheet,
+                sheet_frames(1, 32.0, 32.0),
+                vec![1.0; 1],
+                AnimMode::Loop,
+            ),
+            jump_land: SpriteAnim::new(
+                jl_sheet,
+                sheet_frames(3, 32.0, 32.0),
+                vec![0.06; 3],
+                AnimMode::Once,
+            ),
+
+            // Crouch/slide are drawn squashed to the crouched hitbox height.
+            crouch: SpriteAnim::new(
+                crouch_sheet,
+                sheet_frames(1, 32.0, 32.0),
+                vec![1.0; 1],
+                AnimMode::Loop,
+            ),
+            slide: SpriteAnim::new(
+                slide_sheet,
+                sheet_frames(1, 32.0, 32.0),
+                vec![1.0; 1],
+                AnimMode::Loop,
+            ),
+        }
+    }
 }
 
 struct Player {
@@ -143,6 +437,164 @@ struct Player {
 
     // Jump phase (only meaningful while state == Jump)
     jump_phase: JumpPhase,
+
+    // Jump forgiveness timers (seconds left; <= 0 means inactive)
+    coyote_timer: f32,
+    jump_buffer_timer: f32,
+
+    // Double jump charges left (reset to MAX_AIR_JUMPS on ground)
+    air_jumps_left: u32,
+
+    // Dash timers (seconds left)
+    dash_timer: f32,
+    dash_cooldown: f32,
+
+    // Slide time left (only meaningful while state == Slide)
+    slide_timer: f32,
+
+    // Sprint modifier, sampled on the ground and kept through the air
+    sprinting: bool,
+}
+
+impl Player {
+    /// Standing still on the ground at `pos`, all timers and charges reset.
+    fn spawn(pos: Vec2) -> Self {
+        Self {
+            pos,
+            vel: vec2(0.0, 0.0),
+            facing: 1.0,
+            on_ground: true,
+            ground_mode: MoveState::Idle,
+            state: MoveState::Idle,
+            jump_phase: JumpPhase::Air, // irrelevant until Jump
+            coyote_timer: 0.0,
+            jump_buffer_timer: 0.0,
+            air_jumps_left: MAX_AIR_JUMPS,
+            dash_timer: 0.0,
+            dash_cooldown: 0.0,
+            slide_timer: 0.0,
+            sprinting: false,
+        }
+    }
+
+    /// On the ground and in a plain ground mode (not jumping/dashing/crouched).
+    fn is_free_on_ground(&self) -> bool {
+        self.on_ground
+            && matches!(
+                self.state,
+                MoveState::Idle | MoveState::Walk | MoveState::Run
+            )
+    }
+
+    /// Current hitbox height relative to the standing height.
+    fn height_frac(&self) -> f32 {
+        match self.state {
+            MoveState::Crouch | MoveState::Slide => CROUCH_HEIGHT_FRAC,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Fixed-step bookkeeping: input waiting for the next step, frame time not
+/// simulated yet, and the position before the last step (for interpolation).
+struct StepClock {
+    input: InputState,
+    accumulator: f32,
+    prev_pos: Vec2,
+}
+
+impl StepClock {
+    fn new(pos: Vec2) -> Self {
+        Self {
+            input: InputState::default(),
+            accumulator: 0.0,
+            prev_pos: pos,
+        }
+    }
+
+    /// How far between the last step and the next one we are drawing (0..1).
+    fn alpha(&self) -> f32 {
+        self.accumulator / FIXED_DT
+    }
+
+    /// Bank one frame's time (clamped to MAX_FRAME_DT, which bounds the
+    /// backlog) and call `step` once per whole FIXED_DT. Edges are cleared
+    /// after each step. `step` returns false to stop early and drop the rest
+    /// of the backlog (a finished replay). Returns the number of steps run.
+    fn advance(&mut self, frame_dt: f32, mut step: impl FnMut(&mut StepClock) -> bool) -> usize {
+        self.accumulator += frame_dt.min(MAX_FRAME_DT);
+        let mut steps = 0;
+        while self.accumulator >= FIXED_DT {
+            if !step(self) {
+                self.accumulator = 0.0;
+                break;
+            }
+            steps += 1;
+            self.input.clear_edges();
+            self.accumulator -= FIXED_DT;
+        }
+        steps
+    }
+}
+
+/// Put the player back at `start` with every animation rewound, no pending
+/// step input and no particles, so a run (live, recorded or replayed) always
+/// begins from the same state.
+fn reset_run(
+    player: &mut Player,
+    anims: &mut AnimSet,
+    prev_anim: &mut AnimId,
+    clock: &mut StepClock,
+    particles: &mut Particles,
+    start: Vec2,
+) {
+    *player = Player::spawn(start);
+    *prev_anim = AnimId::Idle;
+    *clock = StepClock::new(start);
+    particles.clear();
+    anims.idle.restart();
+    anims.walk.restart();
+    anims.run.restart();
+    anims.dash.restart();
+    anims.jump_takeoff.restart();
+    anims.jump_rise.restart();
+    anims.jump_apex.restart();
+    anims.jump_fall.restart();
+    anims.jump_land.restart();
+    anims.crouch.restart();
+    anims.slide.restart();
+}
+
+/// Close a recording at `final_pos` and write it out; returns a status line.
+fn save_recording(mut rec: Recording, final_pos: Vec2) -> String {
+    rec.finish(final_pos);
+    match rec.save(RECORDING_PATH) {
+        Ok(()) => format!(
+            "saved {:.1}s to {} (replay with --replay {})",
+            rec.seconds(),
+            RECORDING_PATH,
+            RECORDING_PATH
+        ),
+        Err(err) => format!("recording not saved: {}", err),
+    }
+}
+
+fn any_fallback(anims: &AnimSet) -> bool {
+    [
+        &anims.idle,
+        &anims.walk,
+        &anims.run,
+        &anims.dash,
+        &anims.jump_takeoff,
+        &anims.jump_rise,
+        &anims.jump_apex,
+        &anims.jump_fall,
+        &anims.jump_land,
+        &anims.crouch,
+        &anims.slide,
+    ]
+    .iter()
+    .any(|a| a.is_fallback())
 }
 
 fn sheet_frames(frame_count: usize, frame_w: f32, frame_h: f32) -> Vec<Rect> {
@@ -151,6 +603,411 @@ fn sheet_frames(frame_count: usize, frame_w: f32, frame_h: f32) -> Vec<Rect> {
         .collect()
 }
 
+/// Move `current` toward `target` by at most `max_delta`.
+fn approach(current: f32, target: f32, max_delta: f32) -> f32 {
+    if current < target {
+        (current + max_delta).min(target)
+    } else {
+        (current - max_delta).max(target)
+    }
+}
+
+/// Accelerate vel_x toward target_x using ground/air rates.
+fn accelerate_x(vel_x: f32, target_x: f32, on_ground: bool, dt: f32) -> f32 {
+    let mut accel = if !on_ground {
+        AIR_ACCEL
+    } else if target_x == 0.0 {
+        GROUND_FRICTION
+    } else {
+        GROUND_ACCEL
+    };
+    if vel_x * target_x < 0.0 {
+        accel *= TURN_ACCEL_MULT;
+    }
+    let next = approach(vel_x, target_x, accel * dt);
+    // Only speeding up is capped; anything faster (the end of a dash) bleeds
+    // off through the approach above instead of being cut in one step.
+    if next.abs() > vel_x.abs() {
+        next.clamp(-MAX_GROUND_SPEED, MAX_GROUND_SPEED)
+    } else {
+        next
+    }
+}
+
+/// Enter Jump/Takeoff with upward speed `speed`.
+fn start_jump(player: &mut Player, anims: &mut AnimSet, speed: f32) {
+    player.state = MoveState::Jump;
+    player.jump_phase = JumpPhase::Takeoff;
+    player.on_ground = false;
+    // consume both windows so one press can't fire twice
+    player.jump_buffer_timer = 0.0;
+    player.coyote_timer = 0.0;
+    player.vel.y = -speed;
+    anims.jump_takeoff.restart();
+}
+
+/// Title and a vertical list of rows with `selected` highlighted.
+fn draw_menu(title: &str, rows: &[&str], selected: usize) {
+    let x = screen_width() * 0.5 - 100.0;
+    let y = screen_height() * 0.35;
+    draw_text(title, x, y, 48.0, WHITE);
+    for (i, row) in rows.iter().enumerate() {
+        let color = if i == selected { YELLOW } else { WHITE };
+        let row_y = y + 50.0 + i as f32 * 36.0;
+        draw_text(if i == selected { ">" } else { " " }, x, row_y, 30.0, color);
+        draw_text(row, x + 30.0, row_y, 30.0, color);
+    }
+}
+
+/// Dim whatever has been drawn so far (under pause / prompt menus).
+fn draw_dim_overlay() {
+    draw_rectangle(
+        0.0,
+        0.0,
+        screen_width(),
+        screen_height(),
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    );
+}
+
+/// Fixed-size render target for the world, shown letterboxed in the window.
+struct WorldView {
+    target: RenderTarget,
+    camera: Camera2D,
+    postfx: PostFx,
+    /// CRT look on the blit (the `crt` setting).
+    crt: bool,
+}
+
+impl WorldView {
+    fn new() -> Self {
+        let target = render_target(VIEW_W, VIEW_H);
+        target.texture.set_filter(FilterMode::Nearest);
+        let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, WORLD_W, WORLD_H));
+        camera.render_target = Some(target.clone());
+        Self {
+            target,
+            camera,
+            postfx: PostFx::new(),
+            crt: false,
+        }
+    }
+
+    /// Where the target lands on screen: the largest integer multiple of its
+    /// size that fits (fractional only if the window is smaller than it),
+    /// centred with letterbox bars.
+    fn screen_rect() -> Rect {
+        let (w, h) = (VIEW_W as f32, VIEW_H as f32);
+        let fit = (screen_width() / w).min(screen_height() / h);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let (dw, dh) = (w * scale, h * scale);
+        Rect::new(
+            ((screen_width() - dw) * 0.5).floor(),
+            ((screen_height() - dh) * 0.5).floor(),
+            dw,
+            dh,
+        )
+    }
+
+    /// Draw the world into the target, then blit it to the screen (through
+    /// the post-processing pass).
+    fn draw(
+        &self,
+        player: &Player,
+        clock: &StepClock,
+        anims: &mut AnimSet,
+        stage: &Stage,
+        scale: f32,
+        particles: &Particles,
+    ) {
+        set_camera(&self.camera);
+        clear_background(Color::new(0.05, 0.05, 0.07, 1.0));
+        draw_world(player, clock, anims, stage, scale);
+        particles.draw();
+        set_default_camera();
+
+        self.postfx
+            .blit(&self.target.texture, Self::screen_rect(), self.crt);
+    }
+}
+
+/// Ground line and the player, interpolated between the last two steps.
+/// World space; see `WorldView::draw`.
+fn draw_world(player: &Player, clock: &StepClock, anims: &mut AnimSet, stage: &Stage, scale: f32) {
+    let prev_pos = clock.prev_pos;
+    let sprite_h = 32.0 * scale;
+    // Ground line
+    draw_line(
+        0.0,
+        stage.ground_y + sprite_h,
+        WORLD_W,
+        stage.ground_y + sprite_h,
+        2.0,
+        DARKGRAY,
+    );
+
+    // Snap instead of interpolating across screen wraps.
+    let mut render_pos = prev_pos.lerp(player.pos, clock.alpha());
+    if (player.pos.x - prev_pos.x).abs() > stage.sprite_w {
+        render_pos = player.pos;
+    }
+
+    // Draw only the current anim (advanced by step_player)
+    // (bottom-aligned, squashed to the current hitbox height)
+    let flip_x = player.facing < 0.0;
+    let height_frac = player.height_frac();
+    let draw_pos = vec2(render_pos.x, render_pos.y + sprite_h * (1.0 - height_frac));
+    anim_mut(anims, anim_for(player)).draw(draw_pos, vec2(scale, scale * height_frac), flip_x);
+}
+
+/// Fixed geometry the player moves in.
+struct Stage {
+    ground_y: f32,
+    width: f32,
+    sprite_w: f32,
+}
+
+/// What happened during a step, for cosmetic effects; the simulation never
+/// reads these back.
+#[derive(Default)]
+struct StepEvents {
+    /// A jump started (from the ground or in the air).
+    jumped: bool,
+    /// Touched down, with the downward speed at impact.
+    landed: Option<f32>,
+}
+
+/// Advance the player (input, physics, jump phases, animation) by one fixed step.
+fn step_player(
+    player: &mut Player,
+    anims: &mut AnimSet,
+    prev_anim: &mut AnimId,
+    input: &InputState,
+    stage: &Stage,
+    dt: f32,
+) -> StepEvents {
+    let mut events = StepEvents::default();
+
+    // Facing direction (independent of movement mode)
+    if input.left {
+        player.facing = -1.0;
+    } else if input.right {
+        player.facing = 1.0;
+    }
+
+    // Ground-mode selection keys (only really used when not in Jump/Dash)
+    let cycled = input
+        .mode_cycle_pressed
+        .then_some(match player.ground_mode {
+            MoveState::Idle => MoveState::Walk,
+            MoveState::Walk => MoveState::Run,
+            _ => MoveState::Idle,
+        });
+    if let Some(mode) = input.mode_pressed.or(cycled) {
+        player.ground_mode = mode;
+        if player.is_free_on_ground() {
+            player.state = mode;
+        }
+    }
+
+    // Sprint only changes on the ground, so a running jump keeps its speed.
+    if player.on_ground {
+        player.sprinting = input.sprint;
+    }
+    let speed_mult = if player.sprinting {
+        SPRINT_MULTIPLIER
+    } else {
+        1.0
+    };
+
+    // Crouch while Down is held on the ground; at speed it becomes a slide.
+    if input.down {
+        if player.is_free_on_ground() {
+            if player.vel.x.abs() >= SLIDE_MIN_SPEED {
+                player.state = MoveState::Slide;
+                player.slide_timer = SLIDE_TIME;
+            } else {
+                player.state = MoveState::Crouch;
+            }
+        }
+    } else if player.state == MoveState::Crouch {
+        player.state = player.ground_mode;
+    }
+
+    // Jump start (phase = Takeoff)
+    // A press is remembered for JUMP_BUFFER_TIME, and the ground counts
+    // for COYOTE_TIME after leaving it.
+    if input.jump_pressed {
+        player.jump_buffer_timer = JUMP_BUFFER_TIME;
+    }
+    let can_jump = player.on_ground || player.coyote_timer > 0.0;
+    if player.jump_buffer_timer > 0.0 && can_jump {
+        start_jump(player, anims, JUMP_SPEED);
+        events.jumped = true;
+        // buffered press already released -> behave like a short hop
+        if !input.jump_held {
+            player.vel.y *= 0.45;
+        }
+    } else if input.jump_pressed && player.air_jumps_left > 0 {
+        // Fresh press in the air (coyote window already gone): double jump
+        player.air_jumps_left -= 1;
+        start_jump(player, anims, AIR_JUMP_SPEED);
+        events.jumped = true;
+    }
+    player.jump_buffer_timer -= dt;
+
+    // Dash start (transient, fixed length, then cooldown)
+    if input.dash_pressed && player.dash_cooldown <= 0.0 {
+        player.state = MoveState::Dash;
+        player.vel.x = player.facing * DASH_SPEED;
+        player.vel.y = 0.0;
+        player.dash_timer = DASH_TIME;
+        player.dash_cooldown = DASH_COOLDOWN;
+        anims.dash.restart();
+    }
+    player.dash_cooldown -= dt;
+
+    // Optional: variable jump height (short hop vs full jump)
+    // for shorter jump release J early!
+    if input.jump_released && player.state == MoveState::Jump && player.vel.y < 0.0 {
+        player.vel.y *= 0.45;
+    }
+
+    // ---- Physics -------------------------------------------------------
+    // Horizontal velocity logic depends on state
+    match player.state {
+        MoveState::Dash => {
+            // hold dash speed; when it ends, accel/friction take over
+            player.dash_timer -= dt;
+            if player.dash_timer <= 0.0 {
+                if player.on_ground {
+                    player.state = player.ground_mode;
+                } else {
+                    player.state = MoveState::Jump;
+                    player.jump_phase = JumpPhase::Air;
+                }
+            }
+        }
+
+        MoveState::Crouch => {
+            player.vel.x = approach(player.vel.x, 0.0, GROUND_FRICTION * dt);
+        }
+
+        MoveState::Slide => {
+            // keep most of the momentum, then settle into crouch/stand
+            player.vel.x = approach(player.vel.x, 0.0, SLIDE_FRICTION * dt);
+            player.slide_timer -= dt;
+            if player.slide_timer <= 0.0 {
+                player.state = if input.down {
+                    MoveState::Crouch
+                } else {
+                    player.ground_mode
+                };
+            }
+        }
+
+        MoveState::Jump => {
+            // Air control: follow ground_mode (walk/run) while airborne
+            let air_speed = speed_mult
+                * match player.ground_mode {
+                    MoveState::Run => RUN_SPEED * 0.6,
+                    MoveState::Walk => WALK_SPEED * 0.7,
+                    _ => WALK_SPEED * 0.5,
+                };
+            player.vel.x = accelerate_x(
+                player.vel.x,
+                player.facing * air_speed,
+                player.on_ground,
+                dt,
+            );
+        }
+
+        _ => {
+            // On ground: follow selected ground_mode
+            player.state = player.ground_mode;
+            let target_x = speed_mult
+                * match player.ground_mode {
+                    MoveState::Idle => 0.0,
+                    MoveState::Walk => player.facing * WALK_SPEED,
+                    MoveState::Run => player.facing * RUN_SPEED,
+                    _ => 0.0,
+                };
+            player.vel.x = accelerate_x(player.vel.x, target_x, player.on_ground, dt);
+        }
+    }
+
+    // Apply gravity if airborne (suspended while dashing)
+    if !player.on_ground && player.state != MoveState::Dash {
+        player.vel.y = (player.vel.y + GRAVITY * dt).min(MAX_FALL_SPEED);
+    }
+
+    // Integrate
+    player.pos += player.vel * dt;
+
+    // --- Screen wrap (horizontal) -----------------------------------
+    // If sprite fully exits right -> appear on left
+    if player.pos.x > stage.width {
+        player.pos.x = -stage.sprite_w;
+    }
+
+    // If sprite fully exits left -> appear on right
+    if player.pos.x + stage.sprite_w < 0.0 {
+        player.pos.x = stage.width;
+    }
+
+    // Ground collision detection
+    let mut just_landed = false;
+    if player.pos.y >= stage.ground_y {
+        if !player.on_ground {
+            just_landed = true;
+            events.landed = Some(player.vel.y);
+        }
+        player.pos.y = stage.ground_y;
+        player.vel.y = 0.0;
+        player.on_ground = true;
+    } else {
+        player.on_ground = false;
+    }
+
+    if player.on_ground {
+        player.coyote_timer = COYOTE_TIME;
+        player.air_jumps_left = MAX_AIR_JUMPS;
+    } else {
+        player.coyote_timer -= dt;
+    }
+
+    // ---- Jump phase transitions----------------------------
+    if player.state == MoveState::Jump {
+        // If we just landed: play Landing once
+        if just_landed {
+            player.jump_phase = JumpPhase::Landing;
+            anims.jump_land.restart();
+        }
+
+        // While airborne (and not in takeoff/landing): phase = Air
+        if !player.on_ground && player.jump_phase != JumpPhase::Takeoff {
+            player.jump_phase = JumpPhase::Air;
+        }
+
+        // Takeoff ends when its non-looping anim ends -> switch to Air
+        if player.jump_phase == JumpPhase::Takeoff && anims.jump_takeoff.is_finished() {
+            player.jump_phase = JumpPhase::Air;
+        }
+
+        // Landing ends when its non-looping anim ends -> return to ground_mode
+        if player.jump_phase == JumpPhase::Landing && anims.jump_land.is_finished() {
+            player.state = player.ground_mode;
+            // jump_phase not used outside Jump, but keep it sane
+            player.jump_phase = JumpPhase::Air;
+        }
+    }
+
+    let wanted = anim_for(player);
+    restart_if_changed(anims, prev_anim, wanted);
+    anim_mut(anims, wanted).update(dt);
+    events
+}
+
 fn restart_if_changed(anims: &mut AnimSet, prev: &mut AnimId, next: AnimId) {
     if *prev == next {
         return;
@@ -166,12 +1023,16 @@ fn restart_if_changed(anims: &mut AnimSet, prev: &mut AnimId, next: AnimId) {
         AnimId::JumpApex => anims.jump_apex.restart(),
         AnimId::JumpFall => anims.jump_fall.restart(),
         AnimId::JumpLand => anims.jump_land.restart(),
+        AnimId::Crouch => anims.crouch.restart(),
+        AnimId::Slide => anims.slide.restart(),
     }
 }
 
 fn anim_for(player: &Player) -> AnimId {
     match player.state {
         MoveState::Dash => AnimId::Dash,
+        MoveState::Crouch => AnimId::Crouch,
+        MoveState::Slide => AnimId::Slide,
 
         MoveState::Jump => match player.jump_phase {
             JumpPhase::Takeoff => AnimId::JumpTakeoff,
@@ -198,7 +1059,7 @@ fn anim_for(player: &Player) -> AnimId {
     }
 }
 
-fn anim_mut<'a>(anims: &'a mut AnimSet, id: AnimId) -> &'a mut SpriteAnim {
+fn anim_mut(anims: &mut AnimSet, id: AnimId) -> &mut SpriteAnim {
     match id {
         AnimId::Idle => &mut anims.idle,
         AnimId::Walk => &mut anims.walk,
@@ -209,113 +1070,187 @@ fn anim_mut<'a>(anims: &'a mut AnimSet, id: AnimId) -> &'a mut SpriteAnim {
         AnimId::JumpApex => &mut anims.jump_apex,
         AnimId::JumpFall => &mut anims.jump_fall,
         AnimId::JumpLand => &mut anims.jump_land,
+        AnimId::Crouch => &mut anims.crouch,
+        AnimId::Slide => &mut anims.slide,
     }
 }
 
-#[macroquad::main("Sprite Demo")]
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "Sprite Demo".to_string(),
+        window_width: 1280,
+        window_height: 720,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
 async fn main() {
     let mut game_state = GameState::MainMenu;
 
+    let mut bindings = Bindings::load().await;
+    let mut touch_controls = TouchControls::default();
+    // Controls screen: selected row, screen to return to, waiting for a key,
+    // last status message
+    let mut controls_sel = 0;
+    let mut controls_from = GameState::MainMenu;
+    let mut controls_waiting = false;
+    let mut controls_msg = String::new();
+
+    // Applied before the first frame; the settings screen applies changes live.
+    let mut fullscreen_watch = FullscreenWatch::new();
+    let mut settings = Settings::load().await;
+    if settings.fullscreen {
+        settings.apply(Setting::Fullscreen);
+    }
+    // Settings screen: selected row, screen to return to, last status message
+    let mut settings_sel = 0;
+    let mut settings_from = GameState::MainMenu;
+    let mut settings_msg = String::new();
+
     // === Assets (each state uses a different PNG) ==========================
     // Put these in /assets and ensure they get deployed with gh-pages.
-    let idle_tex = load_texture("assets/idle.png").await.unwrap();
-    let walk_tex = load_texture("assets/idle.png").await.unwrap();
-    let run_tex = load_texture("assets/idle.png").await.unwrap();
-    let dash_tex = load_texture("assets/idle.png").await.unwrap();
-
-    let jt_tex = load_texture("assets/idle.png").await.unwrap();
-    let jr_tex = load_texture("assets/idle.png").await.unwrap();
-    let ja_tex = load_texture("assets/idle.png").await.unwrap();
-    let jf_tex = load_texture("assets/idle.png").await.unwrap();
-    let jl_tex = load_texture("assets/idle.png").await.unwrap();
-
-    for t in [
-        &idle_tex, &walk_tex, &run_tex, &dash_tex, &jt_tex, &jr_tex, &ja_tex, &jf_tex, &jl_tex,
-    ] {
-        t.set_filter(FilterMode::Nearest);
-    }
-
-    // each sheet is 1 row, frames are 32x32.
-    let mut anims = AnimSet {
-        idle: SpriteAnim::new(
-            idle_tex,
-            sheet_frames(5, 32.0, 32.0),
-            vec![0.20; 5],
-            AnimMode::Loop,
-        ),
-        walk: SpriteAnim::new(
-            walk_tex,
-            sheet_frames(4, 32.0, 32.0),
-            vec![0.12; 4],
-            AnimMode::Loop,
-        ),
-        run: SpriteAnim::new(
-            run_tex,
-            sheet_frames(4, 32.0, 32.0),
-            vec![0.08; 4],
-            AnimMode::Loop,
-        ),
-        dash: SpriteAnim::new(
-            dash_tex,
-            sheet_frames(3, 32.0, 32.0),
-            vec![0.06; 3],
-            AnimMode::Loop,
-        ),
-
-        // Jump phases: takeoff + landing are "Once". Air poses can be 1-frame "Loop" (held).
-        jump_takeoff: SpriteAnim::new(
-            jt_tex,
-            sheet_frames(3, 32.0, 32.0),
-            vec![0.06; 3],
-            AnimMode::Once,
-        ),
-        jump_rise: SpriteAnim::new(
-            jr_tex,
-            sheet_frames(1, 32.0, 32.0),
-            vec![1.0; 1],
-            AnimMode::Loop,
-        ),
-        jump_apex: SpriteAnim::new(
-            ja_tex,
-            sheet_frames(1, 32.0, 32.0),
-            vec![1.0; 1],
-            AnimMode::Loop,
-        ),
-        jump_fall: SpriteAnim::new(
-            jf_tex,
-            sheet_frames(1, 32.0, 32.0),
-            vec![1.0; 1],
-            AnimMode::Loop,
-        ),
-        jump_land: SpriteAnim::new(
-            jl_tex,
-            sheet_frames(3, 32.0, 32.0),
-            vec![0.06; 3],
-            AnimMode::Once,
-        ),
-    };
+    // Missing files fall back to colored blocks (one color per state).
+    let idle_sheet = load_sprite_renderer("assets/idle.png", GRAY).await;
+    let walk_sheet = load_sprite_renderer("assets/idle.png", SKYBLUE).await;
+    let run_sheet = load_sprite_renderer("assets/idle.png", BLUE).await;
+    let dash_sheet = load_sprite_renderer("assets/idle.png", ORANGE).await;
+
+    let jt_sheet = load_sprite_renderer("assets/idle.png", LIME).await;
+    let jr_sheet = load_sprite_renderer("assets/idle.png", GREEN).await;
+    let ja_sheet = load_sprite_renderer("assets/idle.png", YELLOW).await;
+    let jf_sheet = load_sprite_renderer("assets/idle.png", GOLD).await;
+    let jl_sheet = load_sprite_renderer("assets/idle.png", DARKGREEN).await;
+
+    let crouch_sheet = load_sprite_renderer("assets/idle.png", PURPLE).await;
+    let slide_sheet = load_sprite_renderer("assets/idle.png", MAGENTA).await;
+
+    let mut anims = AnimSet::new([
+        idle_sheet,
+        walk_sheet,
+        run_sheet,
+        dash_sheet,
+        jt_sheet,
+        jr_sheet,
+        ja_sheet,
+        jf_sheet,
+        jl_sheet,
+        crouch_sheet,
+        slide_sheet,
+    ]);
 
     let scale = 6.0;
     let sprite_w = 32.0 * scale;
     let sprite_h = 32.0 * scale;
 
-    let ground_y = screen_height() * 0.75 - sprite_h;
-    let start_x = screen_width() * 0.5 - sprite_w * 0.5;
-
-    let mut player = Player {
-        pos: vec2(start_x, ground_y),
-        vel: vec2(0.0, 0.0),
-        facing: 1.0,
-        on_ground: true,
-        ground_mode: MoveState::Idle,
-        state: MoveState::Idle,
-        jump_phase: JumpPhase::Air, // irrelevant until Jump
-    };
+    let ground_y = WORLD_H * 0.75 - sprite_h;
+    let start_x = WORLD_W * 0.5 - sprite_w * 0.5;
+    let mut world_view = WorldView::new();
+
+    let mut player = Player::spawn(vec2(start_x, ground_y));
 
     let mut prev_anim = AnimId::Idle;
 
+    let mut clock = StepClock::new(player.pos);
+    let mut particles = Particles::new();
+
+    // Replays: F9 records the live run, `--replay <path>` plays one back.
+    let mut recording: Option<Recording> = None;
+    let mut playback: Option<Playback> = None;
+    let mut replay_msg = String::new();
+
+    // Attract mode: the bundled demo replays after ATTRACT_DELAY idle on the menu.
+    let attract_demo = match Recording::load(ATTRACT_DEMO_PATH).await {
+        Ok(rec) => Some(rec),
+        Err(err) => {
+            warn!("no attract demo ({}): {}", ATTRACT_DEMO_PATH, err);
+            None
+        }
+    };
+    let mut attract = false;
+    let mut menu_idle = 0.0;
+    // Pause menu: selected row (reset each time the game is paused)
+    let mut pause_sel = 0;
+    // Quit prompt: selected row and the screen to return to on "No".
+    // Closing the window asks too, instead of quitting outright.
+    let mut quit_sel = 0;
+    let mut quit_from = GameState::MainMenu;
+    prevent_quit();
+    if let Some(path) = std::env::args().skip_while(|a| a != "--replay").nth(1) {
+        match Recording::load(&path)
+            .await
+            .and_then(|rec| Playback::new(rec, FIXED_DT))
+        {
+            Ok(pb) => {
+                info!(
+                    "replaying {} ({} steps, {:.1}s)",
+                    path,
+                    pb.recording().len(),
+                    pb.recording().seconds()
+                );
+                reset_run(
+                    &mut player,
+                    &mut anims,
+                    &mut prev_anim,
+                    &mut clock,
+                    &mut particles,
+                    pb.recording().start,
+                );
+                playback = Some(pb);
+                game_state = GameState::Demo;
+            }
+            Err(err) => {
+                warn!("cannot replay {}: {}", path, err);
+                replay_msg = format!("cannot replay {}: {}", path, err);
+            }
+        }
+    }
+
     loop {
+        if is_quit_requested() {
+            // closing again while asked means it
+            if game_state == GameState::ConfirmQuit {
+                break;
+            }
+            // a run in progress is paused under the prompt
+            quit_from = match game_state {
+                GameState::Demo | GameState::Paused => GameState::Paused,
+                other => other,
+            };
+            pause_sel = 0;
+            quit_sel = 0;
+            // "No" must not land back on a pending rebind
+            controls_waiting = false;
+            game_state = GameState::ConfirmQuit;
+        }
+
+        // Unclamped; StepClock::advance bounds what the simulation sees.
         let dt = get_frame_time();
+        let touch_frame = touch_controls.update();
+        let input = InputState::poll(&bindings, &touch_frame);
+
+        if fullscreen_watch.update(&mut settings)
+            && let Err(err) = settings.save()
+        {
+            warn!("left fullscreen, but not saved: {}", err);
+        }
+        if input.fullscreen_pressed {
+            settings.toggle(Setting::Fullscreen);
+            if let Err(err) = settings.save() {
+                warn!("fullscreen toggled, but not saved: {}", err);
+            }
+        }
+        // Recorded runs keep the stage they were made with (older ones may
+        // predate the fixed world size).
+        let recorded = recording
+            .as_ref()
+            .or(playback.as_ref().map(|pb| pb.recording()));
+        let stage = Stage {
+            ground_y: recorded.map_or(ground_y, |rec| rec.ground_y),
+            width: recorded.map_or(WORLD_W, |rec| rec.stage_width),
+            sprite_w,
+        };
+        world_view.crt = settings.crt;
         clear_background(BLACK);
 
         match game_state {
@@ -328,249 +1263,772 @@ async fn main() {
                     WHITE,
                 );
                 draw_text(
-                    "ENTER: start  |  ESC: quit",
-                    screen_width() * 0.5 - 180.0,
+                    &format!(
+                        "{}: start  |  C: controls  |  S: settings{}",
+                        key_name(bindings.key(Action::Confirm)).to_uppercase(),
+                        if cfg!(target_arch = "wasm32") {
+                            ""
+                        } else {
+                            "  |  ESC: quit"
+                        }
+                    ),
+                    screen_width() * 0.5 - 240.0,
                     screen_height() * 0.55,
                     28.0,
                     WHITE,
                 );
-                if is_key_pressed(KeyCode::Enter) {
+                draw_text(
+                    &replay_msg,
+                    screen_width() * 0.5 - 240.0,
+                    screen_height() * 0.55 + 40.0,
+                    22.0,
+                    ORANGE,
+                );
+                if input.controls_pressed {
+                    game_state = GameState::Controls;
+                    controls_from = GameState::MainMenu;
+                    controls_sel = 0;
+                    controls_waiting = false;
+                    controls_msg.clear();
+                } else if input.settings_pressed {
+                    game_state = GameState::Settings;
+                    settings_from = GameState::MainMenu;
+                    settings_sel = 0;
+                    settings_msg.clear();
+                } else if input.confirm_pressed {
                     game_state = GameState::Demo;
-
-                    player.pos = vec2(start_x, ground_y);
-                    player.vel = vec2(0.0, 0.0);
-                    player.facing = 1.0;
-                    player.on_ground = true;
-                    player.ground_mode = MoveState::Idle;
-                    player.state = MoveState::Idle;
-                    prev_anim = AnimId::Idle;
-                    // restart all for consistency
-                    anims.idle.restart();
-                    anims.walk.restart();
-                    anims.run.restart();
-                    anims.dash.restart();
-                    anims.jump_takeoff.restart();
-                    anims.jump_rise.restart();
-                    anims.jump_apex.restart();
-                    anims.jump_fall.restart();
-                    anims.jump_land.restart();
+                    reset_run(
+                        &mut player,
+                        &mut anims,
+                        &mut prev_anim,
+                        &mut clock,
+                        &mut particles,
+                        vec2(start_x, ground_y),
+                    );
+                } else if input.back_pressed && !cfg!(target_arch = "wasm32") {
+                    quit_from = GameState::MainMenu;
+                    quit_sel = 0;
+                    game_state = GameState::ConfirmQuit;
                 }
-                if is_key_pressed(KeyCode::Escape) {
-                    std::process::exit(0);
+
+                menu_idle = if input.any_key.is_some() || input.confirm_pressed {
+                    0.0
+                } else {
+                    menu_idle + dt
+                };
+                if game_state == GameState::MainMenu && menu_idle >= ATTRACT_DELAY {
+                    menu_idle = 0.0;
+                    match attract_demo
+                        .clone()
+                        .ok_or_else(|| "not loaded".to_string())
+                        .and_then(|rec| Playback::new(rec, FIXED_DT))
+                    {
+                        Ok(pb) => {
+                            game_state = GameState::Demo;
+                            attract = true;
+                            reset_run(
+                                &mut player,
+                                &mut anims,
+                                &mut prev_anim,
+                                &mut clock,
+                                &mut particles,
+                                pb.recording().start,
+                            );
+                            playback = Some(pb);
+                        }
+                        Err(err) => warn!("attract demo unavailable: {}", err),
+                    }
                 }
             }
 
             GameState::Demo => {
-                // ---- UI + Controls -------------------------------------------------
-                draw_text(
-                    "I:Idle  W:Walk  R:Run  J:Jump  D:Dash    <-/->:face    ESC: menu",
-                    20.0,
-                    30.0,
-                    22.0,
+                // ---- Attract mode --------------------------------------------------
+                // Confirm starts a real run right away; any other key (or the
+                // demo running out) goes back to the menu.
+                if attract {
+                    let finished = playback.as_ref().is_none_or(|pb| pb.is_finished());
+                    if input.confirm_pressed {
+                        attract = false;
+                        playback = None;
+                        reset_run(
+                            &mut player,
+                            &mut anims,
+                            &mut prev_anim,
+                            &mut clock,
+                            &mut particles,
+                            vec2(start_x, ground_y),
+                        );
+                    } else if input.any_key.is_some() || finished {
+                        attract = false;
+                        playback = None;
+                        game_state = GameState::MainMenu;
+                        // end the frame so the menu doesn't see this key press too
+                        next_frame().await;
+                        continue;
+                    }
+                }
+
+                // ---- Recording -----------------------------------------------------
+                if playback.is_none() && input.record_pressed {
+                    if let Some(rec) = recording.take() {
+                        replay_msg = save_recording(rec, player.pos);
+                    } else {
+                        // Recordings always start from a fresh run.
+                        let start = vec2(start_x, ground_y);
+                        reset_run(
+                            &mut player,
+                            &mut anims,
+                            &mut prev_anim,
+                            &mut clock,
+                            &mut particles,
+                            start,
+                        );
+                        recording = Some(Recording::new(FIXED_DT, WORLD_W, ground_y, start));
+                        replay_msg.clear();
+                    }
+                }
+
+                // Takes effect next frame; this one still steps and draws.
+                if input.back_pressed {
+                    game_state = GameState::Paused;
+                    pause_sel = 0;
+                }
+
+                // ---- Fixed-step simulation -----------------------------------------
+                clock.input.accumulate(&input);
+                clock.advance(dt, |clock| {
+                    // During playback the recording replaces live input.
+                    let replayed = match &mut playback {
+                        Some(pb) => match pb.next_input() {
+                            Some(input) => Some(input.clone()),
+                            None => {
+                                clock.prev_pos = player.pos;
+                                return false;
+                            }
+                        },
+                        None => None,
+                    };
+                    clock.prev_pos = player.pos;
+                    let events = step_player(
+                        &mut player,
+                        &mut anims,
+                        &mut prev_anim,
+                        replayed.as_ref().unwrap_or(&clock.input),
+                        &stage,
+                        FIXED_DT,
+                    );
+                    let feet = player.pos + vec2(sprite_w * 0.5, sprite_h);
+                    if let Some(impact) = events.landed {
+                        particles.landing_dust(feet, impact);
+                    }
+                    if events.jumped {
+                        particles.jump_puff(feet);
+                    }
+                    if let Some(pb) = &mut playback {
+                        pb.check(player.pos);
+                    }
+                    if let Some(rec) = &mut recording {
+                        rec.record(&clock.input, player.pos);
+                    }
+                    true
+                });
+                particles.update(dt);
+
+                // ---- Draw ----------------------------------------------------------
+                world_view.draw(&player, &clock, &mut anims, &stage, scale, &particles);
+
+                // ---- HUD (screen space, on top of the world) ------------------------
+                // Laid out in world units (1280x720) and mapped onto the letterboxed
+                // view, so it stays inside the picture and scales with it.
+                let view = WorldView::screen_rect();
+                let u = view.h / WORLD_H;
+                let hud_line = |text: &str, row: f32, color: Color| {
+                    draw_text(
+                        text,
+                        view.x + 20.0 * u,
+                        view.y + (30.0 + 30.0 * row) * u,
+                        22.0 * u,
+                        color,
+                    );
+                };
+                let k = |a| key_name(bindings.key(a));
+                hud_line(
+                    &format!(
+                        "{}/{}/{}:mode {}:sprint {}:jump {}:dash {}:crouch {}/{}:face ESC:pause",
+                        k(Action::ModeIdle),
+                        k(Action::ModeWalk),
+                        k(Action::ModeRun),
+                        k(Action::Sprint),
+                        k(Action::Jump),
+                        k(Action::Dash),
+                        k(Action::Crouch),
+                        k(Action::MoveLeft),
+                        k(Action::MoveRight),
+                    ),
+                    0.0,
                     WHITE,
                 );
-                draw_text(
+                hud_line(
                     &format!(
-                        "State: {:?}   GroundMode: {:?}   JumpPhase: {:?}",
-                        player.state, player.ground_mode, player.jump_phase
+                        "State: {:?}   GroundMode: {:?}   JumpPhase: {:?}{}",
+                        player.state,
+                        player.ground_mode,
+                        player.jump_phase,
+                        if player.sprinting { "   SPRINT" } else { "" }
                     ),
-                    20.0,
-                    60.0,
-                    22.0,
+                    1.0,
                     YELLOW,
                 );
-                draw_text(&format!("vy: {:.1}", player.vel.y), 20.0, 90.0, 22.0, GREEN);
-
-                if is_key_pressed(KeyCode::Escape) {
-                    game_state = GameState::MainMenu;
-                    continue;
+                hud_line(
+                    &format!(
+                        "vx: {:.1}   vy: {:.1}   Air jumps: {}/{}   Dash: {}",
+                        player.vel.x,
+                        player.vel.y,
+                        player.air_jumps_left,
+                        MAX_AIR_JUMPS,
+                        if player.dash_cooldown > 0.0 {
+                            format!("{:.1}s", player.dash_cooldown)
+                        } else {
+                            "ready".to_string()
+                        }
+                    ),
+                    2.0,
+                    GREEN,
+                );
+                if any_fallback(&anims) {
+                    hud_line("Renderer: fallback (sprite textures missing)", 3.0, RED);
                 }
-
-                // Facing direction (independent of movement mode)
-                if is_key_down(KeyCode::Left) {
-                    player.facing = -1.0;
-                } else if is_key_down(KeyCode::Right) {
-                    player.facing = 1.0;
+                if let Some(rec) = &recording {
+                    hud_line(
+                        &format!("REC {:.1}s  (F9: stop and save)", rec.seconds()),
+                        4.0,
+                        RED,
+                    );
+                } else if let Some(pb) = &playback {
+                    let status = if !pb.is_finished() {
+                        format!("REPLAY step {}/{}", pb.step(), pb.recording().len())
+                    } else if pb.diverged() {
+                        "REPLAY finished: DIVERGED from recording".to_string()
+                    } else {
+                        "REPLAY finished: matches recording".to_string()
+                    };
+                    if attract {
+                        let caption = "DEMO  -  press any key";
+                        let size = 40.0 * u;
+                        let dims = measure_text(caption, None, size as u16, 1.0);
+                        draw_text(
+                            caption,
+                            view.x + (view.w - dims.width) * 0.5,
+                            view.y + view.h * 0.2,
+                            size,
+                            WHITE,
+                        );
+                    } else {
+                        hud_line(&status, 4.0, SKYBLUE);
+                    }
+                } else {
+                    hud_line(&replay_msg, 4.0, ORANGE);
                 }
 
-                // Ground-mode selection keys (only really used when not in Jump/Dash)
-                if is_key_pressed(KeyCode::I) {
-                    player.ground_mode = MoveState::Idle;
-                    if player.on_ground
-                        && player.state != MoveState::Dash
-                        && player.state != MoveState::Jump
-                    {
-                        player.state = MoveState::Idle;
-                    }
+                touch_controls.draw(&touch_frame);
+            }
+
+            GameState::Paused => {
+                // World stays drawn, frozen, under a dim overlay.
+                world_view.draw(&player, &clock, &mut anims, &stage, scale, &particles);
+                draw_dim_overlay();
+                let rows: Vec<&str> = PAUSE_OPTIONS.iter().map(|o| o.label()).collect();
+                draw_menu("Paused", &rows, pause_sel);
+
+                if input.menu_up {
+                    pause_sel = (pause_sel + PAUSE_OPTIONS.len() - 1) % PAUSE_OPTIONS.len();
                 }
-                if is_key_pressed(KeyCode::W) {
-                    player.ground_mode = MoveState::Walk;
-                    if player.on_ground
-                        && player.state != MoveState::Dash
-                        && player.state != MoveState::Jump
-                    {
-                        player.state = MoveState::Walk;
-                    }
+                if input.menu_down {
+                    pause_sel = (pause_sel + 1) % PAUSE_OPTIONS.len();
                 }
-                if is_key_pressed(KeyCode::R) {
-                    player.ground_mode = MoveState::Run;
-                    if player.on_ground
-                        && player.state != MoveState::Dash
-                        && player.state != MoveState::Jump
-                    {
-                        player.state = MoveState::Run;
+                if input.back_pressed {
+                    game_state = GameState::Demo;
+                } else if input.confirm_pressed {
+                    match PAUSE_OPTIONS[pause_sel] {
+                        PauseOption::Resume => game_state = GameState::Demo,
+                        // Replays start over, recordings start afresh
+                        PauseOption::Restart => {
+                            game_state = GameState::Demo;
+                            let start = match playback.take() {
+                                Some(pb) => {
+                                    let start = pb.recording().start;
+                                    playback = Playback::new(pb.recording().clone(), FIXED_DT).ok();
+                                    start
+                                }
+                                None => vec2(start_x, ground_y),
+                            };
+                            reset_run(
+                                &mut player,
+                                &mut anims,
+                                &mut prev_anim,
+                                &mut clock,
+                                &mut particles,
+                                start,
+                            );
+                            if recording.is_some() {
+                                recording =
+                                    Some(Recording::new(FIXED_DT, WORLD_W, ground_y, start));
+                            }
+                        }
+                        // Saves a recording in progress
+                        PauseOption::QuitToMenu => {
+                            if let Some(rec) = recording.take() {
+                                replay_msg = save_recording(rec, player.pos);
+                            }
+                            playback = None;
+                            attract = false;
+                            game_state = GameState::MainMenu;
+                            // end the frame so the menu doesn't see this Confirm too
+                            next_frame().await;
+                            continue;
+                        }
+                        PauseOption::Controls => {
+                            game_state = GameState::Controls;
+                            controls_from = GameState::Paused;
+                            controls_sel = 0;
+                            controls_waiting = false;
+                            controls_msg.clear();
+                        }
+                        PauseOption::Settings => {
+                            game_state = GameState::Settings;
+                            settings_from = GameState::Paused;
+                            settings_sel = 0;
+                            settings_msg.clear();
+                        }
+                        PauseOption::QuitGame => {
+                            quit_from = GameState::Paused;
+                            quit_sel = 0;
+                            game_state = GameState::ConfirmQuit;
+                        }
                     }
                 }
+            }
 
-                // Jump start (phase = Takeoff)
-                if is_key_pressed(KeyCode::J) && player.on_ground {
-                    player.state = MoveState::Jump;
-                    player.jump_phase = JumpPhase::Takeoff;
-                    player.on_ground = false;
-
-                    // TODO: Variable jump height can be added by cutting vy on key release.
-                    player.vel.y = -900.0;
-
-                    anims.jump_takeoff.restart();
+            GameState::Settings => {
+                // Opened from a run: keep the frozen world visible underneath.
+                if settings_from == GameState::Paused {
+                    world_view.draw(&player, &clock, &mut anims, &stage, scale, &particles);
+                    draw_dim_overlay();
                 }
+                let rows: Vec<String> = Setting::ALL
+                    .iter()
+                    .map(|s| {
+                        let value = if settings.flag(*s) { "on" } else { "off" };
+                        format!("{}: {}", s.label(), value)
+                    })
+                    .collect();
+                let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+                draw_menu("Settings", &rows, settings_sel);
+                let footer_y = screen_height() * 0.35 + 50.0 + rows.len() as f32 * 36.0 + 20.0;
+                let x = screen_width() * 0.5 - 100.0;
+                draw_text(
+                    &format!(
+                        "Up/Down: select  {}: toggle  ESC: back",
+                        key_name(bindings.key(Action::Confirm))
+                    ),
+                    x,
+                    footer_y,
+                    22.0,
+                    GRAY,
+                );
+                draw_text(&settings_msg, x, footer_y + 30.0, 22.0, ORANGE);
 
-                // Dash start (transient)
-                if is_key_pressed(KeyCode::D) {
-                    player.state = MoveState::Dash;
-                    player.vel.x = player.facing * 1200.0;
-                    anims.dash.restart();
+                if input.menu_up {
+                    settings_sel = (settings_sel + Setting::ALL.len() - 1) % Setting::ALL.len();
+                }
+                if input.menu_down {
+                    settings_sel = (settings_sel + 1) % Setting::ALL.len();
+                }
+                if input.confirm_pressed {
+                    settings.toggle(Setting::ALL[settings_sel]);
+                    settings_msg = match settings.save() {
+                        Ok(()) => String::new(),
+                        Err(err) => format!("applied, but not saved: {}", err),
+                    };
+                }
+                if input.back_pressed {
+                    game_state = settings_from;
                 }
+            }
 
-                // Optional: variable jump height (short hop vs full jump)
-                // for shorter jump release J early!
-                if is_key_released(KeyCode::J)
-                    && player.state == MoveState::Jump
-                    && player.vel.y < 0.0
-                {
-                    player.vel.y *= 0.45;
+            GameState::ConfirmQuit => {
+                // Asked from a run: keep the frozen world visible underneath.
+                if quit_from == GameState::Paused {
+                    world_view.draw(&player, &clock, &mut anims, &stage, scale, &particles);
+                    draw_dim_overlay();
                 }
+                draw_menu("Quit the game?", &QUIT_OPTIONS, quit_sel);
 
-                // ---- Physics -------------------------------------------------------
-                let gravity = 2200.0;
-                let (walk_speed, run_speed) = (250.0, 450.0);
-
-                // Horizontal velocity logic depends on state
-                match player.state {
-                    MoveState::Dash => {
-                        // decay dash; once it slows down, return to ground mode
-                        player.vel.x *= 0.88;
-                        if player.vel.x.abs() < 80.0 {
-                            player.state = player.ground_mode;
-                            if player.on_ground {
-                                player.vel.x = 0.0;
-                            }
-                        }
+                if input.menu_up || input.menu_down {
+                    quit_sel = 1 - quit_sel;
+                }
+                if input.back_pressed {
+                    game_state = quit_from;
+                } else if input.confirm_pressed {
+                    if quit_sel == 1 {
+                        break;
                     }
+                    game_state = quit_from;
+                }
+            }
 
-                    MoveState::Jump => {
-                        // Air control: follow ground_mode (walk/run) while airborne
-                        let air_speed = match player.ground_mode {
-                            MoveState::Run => run_speed * 0.6,
-                            MoveState::Walk => walk_speed * 0.7,
-                            _ => walk_speed * 0.5,
+            GameState::Controls => {
+                // Opened from a run: keep the frozen world visible underneath.
+                if controls_from == GameState::Paused {
+                    world_view.draw(&player, &clock, &mut anims, &stage, scale, &particles);
+                    draw_dim_overlay();
+                }
+                draw_text("Controls", 40.0, 60.0, 40.0, WHITE);
+                for (i, action) in Action::ALL.iter().enumerate() {
+                    let selected = i == controls_sel;
+                    let key = if selected && controls_waiting {
+                        "press a key...".to_string()
+                    } else {
+                        key_name(bindings.key(*action))
+                    };
+                    let y = 110.0 + i as f32 * 30.0;
+                    let color = if selected { YELLOW } else { WHITE };
+                    draw_text(if selected { ">" } else { " " }, 40.0, y, 26.0, color);
+                    draw_text(action.label(), 70.0, y, 26.0, color);
+                    draw_text(&key, 300.0, y, 26.0, color);
+                }
+                let footer_y = 110.0 + Action::ALL.len() as f32 * 30.0 + 20.0;
+                draw_text(
+                    &format!(
+                        "Up/Down: select  {}: rebind  ESC: back",
+                        key_name(bindings.key(Action::Confirm))
+                    ),
+                    40.0,
+                    footer_y,
+                    22.0,
+                    GRAY,
+                );
+                draw_text(&controls_msg, 40.0, footer_y + 30.0, 22.0, ORANGE);
+
+                if controls_waiting {
+                    // Escape cancels; any other key is offered to the selected action.
+                    if input.back_pressed {
+                        controls_waiting = false;
+                        controls_msg.clear();
+                    } else if let Some(key) = input.any_key {
+                        controls_waiting = false;
+                        let action = Action::ALL[controls_sel];
+                        controls_msg = match bindings.set(action, key) {
+                            Ok(()) => match bindings.save() {
+                                Ok(()) => format!("{} -> {}", action.label(), key_name(key)),
+                                Err(err) => format!("bound, but not saved: {}", err),
+                            },
+                            Err(err) => format!("rejected: {}", err),
                         };
-                        player.vel.x = player.facing * air_speed;
                     }
-
-                    _ => {
-                        // On ground: follow selected ground_mode
-                        player.state = player.ground_mode;
-                        player.vel.x = match player.ground_mode {
-                            MoveState::Idle => 0.0,
-                            MoveState::Walk => player.facing * walk_speed,
-                            MoveState::Run => player.facing * run_speed,
-                            _ => 0.0,
-                        };
+                } else {
+                    if input.menu_up {
+                        controls_sel = (controls_sel + Action::ALL.len() - 1) % Action::ALL.len();
+                    }
+                    if input.menu_down {
+                        controls_sel = (controls_sel + 1) % Action::ALL.len();
+                    }
+                    if input.confirm_pressed {
+                        controls_waiting = true;
+                        controls_msg.clear();
+                    }
+                    if input.back_pressed {
+                        game_state = controls_from;
                     }
                 }
+            }
+        }
 
-                // Apply gravity if airborne
-                if !player.on_ground {
-                    player.vel.y += gravity * dt;
-                }
+        if settings.show_fps {
+            draw_text(
+                &format!("FPS: {}", get_fps()),
+                screen_width() - 110.0,
+                30.0,
+                22.0,
+                WHITE,
+            );
+        }
 
-                // Integrate
-                player.pos += player.vel * dt;
+        next_frame().await;
+    }
 
-                // --- Screen wrap (horizontal) -----------------------------------
-                let screen_w = screen_width();
+    // Leaving the loop ends the program; don't lose a recording in progress.
+    if let Some(rec) = recording.take() {
+        info!("{}", save_recording(rec, player.pos));
+    }
+}
 
-                // If sprite fully exits right -> appear on left
-                if player.pos.x > screen_w {
-                    player.pos.x = -sprite_w;
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                // If sprite fully exits left -> appear on right
-                if player.pos.x + sprite_w < 0.0 {
-                    player.pos.x = screen_w;
-                }
+    /// Every animation on the colored-block renderer, as with no assets.
+    fn fallback_anims() -> AnimSet {
+        AnimSet::new(std::array::from_fn(|_| SpriteRenderer::Fallback(GRAY)))
+    }
 
-                // Ground collision detection
-                let mut just_landed = false;
-                if player.pos.y >= ground_y {
-                    if !player.on_ground {
-                        just_landed = true;
-                    }
-                    player.pos.y = ground_y;
-                    player.vel.y = 0.0;
-                    player.on_ground = true;
-                } else {
-                    player.on_ground = false;
-                }
+    /// The demo's stage: 1280 px wide, 32 px sprites drawn at 6x.
+    fn test_stage() -> Stage {
+        Stage {
+            ground_y: 348.0,
+            width: WORLD_W,
+            sprite_w: 192.0,
+        }
+    }
 
-                // ---- Jump phase transitions----------------------------
-                if player.state == MoveState::Jump {
-                    // If we just landed: play Landing once
-                    if just_landed {
-                        player.jump_phase = JumpPhase::Landing;
-                        anims.jump_land.restart();
-                    }
+    /// A player on the test stage, stepped at FIXED_DT with scripted input.
+    struct Sim {
+        player: Player,
+        anims: AnimSet,
+        prev_anim: AnimId,
+        stage: Stage,
+    }
 
-                    // While airborne (and not in takeoff/landing): phase = Air
-                    if !player.on_ground && player.jump_phase != JumpPhase::Takeoff {
-                        player.jump_phase = JumpPhase::Air;
-                    }
+    impl Sim {
+        fn new() -> Self {
+            let stage = test_stage();
+            Self {
+                player: Player::spawn(vec2(544.0, stage.ground_y)),
+                anims: fallback_anims(),
+                prev_anim: AnimId::Idle,
+                stage,
+            }
+        }
 
-                    // Takeoff ends when its non-looping anim ends -> switch to Air
-                    if player.jump_phase == JumpPhase::Takeoff && anims.jump_takeoff.is_finished() {
-                        player.jump_phase = JumpPhase::Air;
-                    }
+        fn step(&mut self, input: &InputState) -> StepEvents {
+            step_player(
+                &mut self.player,
+                &mut self.anims,
+                &mut self.prev_anim,
+                input,
+                &self.stage,
+                FIXED_DT,
+            )
+        }
+    }
 
-                    // Landing ends when its non-looping anim ends -> return to ground_mode
-                    if player.jump_phase == JumpPhase::Landing && anims.jump_land.is_finished() {
-                        player.state = player.ground_mode;
-                        // jump_phase not used outside Jump, but keep it sane
-                        player.jump_phase = JumpPhase::Air;
-                    }
-                }
+    fn press() -> InputState {
+        InputState {
+            jump_pressed: true,
+            jump_held: true,
+            ..Default::default()
+        }
+    }
 
-                // ---- Draw ----------------------------------------------------------
-                // Ground line
-                draw_line(
-                    0.0,
-                    ground_y + sprite_h,
-                    screen_width(),
-                    ground_y + sprite_h,
-                    2.0,
-                    DARKGRAY,
-                );
+    fn hold() -> InputState {
+        InputState {
+            jump_held: true,
+            ..Default::default()
+        }
+    }
+
+    fn release() -> InputState {
+        InputState {
+            jump_released: true,
+            ..Default::default()
+        }
+    }
 
-                let wanted = anim_for(&player);
-                restart_if_changed(&mut anims, &mut prev_anim, wanted);
+    #[test]
+    fn missing_texture_uses_fallback() {
+        let renderer = choose_renderer("assets/missing.png", Err("not found"), ORANGE);
+        assert!(matches!(renderer, SpriteRenderer::Fallback(c) if c == ORANGE));
+    }
+
+    /// A real texture needs a GL context (load_texture and Texture2D::empty
+    /// both go through macroquad's context, which tests don't have), so this
+    /// wraps a dummy raw id that is never drawn or freed.
+    fn dummy_textured() -> SpriteRenderer {
+        let id = miniquad::TextureId::from_raw_id(miniquad::RawId::OpenGl(0));
+        SpriteRenderer::Textured(Texture2D::from_miniquad_texture(id))
+    }
+
+    #[test]
+    fn any_fallback_sees_fallback_renderers() {
+        assert!(any_fallback(&fallback_anims()));
+
+        let mut anims = AnimSet::new(std::array::from_fn(|_| dummy_textured()));
+        assert!(!any_fallback(&anims));
+        anims.slide.renderer = SpriteRenderer::Fallback(MAGENTA);
+        assert!(any_fallback(&anims));
+    }
+
+    #[test]
+    fn fall_speed_is_clamped() {
+        // There is no tile map; a 32 px sprite cell is the smallest thing
+        // the player could tunnel through.
+        const TILE_H: f32 = 32.0;
+        let dt = 1.0 / 60.0;
+        let mut anims = fallback_anims();
+        let mut prev_anim = AnimId::JumpFall;
+        let mut player = Player::spawn(vec2(544.0, 0.0));
+        player.on_ground = false;
+        player.state = MoveState::Jump;
+        player.vel.y = 10.0 * MAX_FALL_SPEED;
+
+        let start_y = player.pos.y;
+        let input = InputState::default();
+        step_player(
+            &mut player,
+            &mut anims,
+            &mut prev_anim,
+            &input,
+            &test_stage(),
+            dt,
+        );
+
+        assert_eq!(player.vel.y, MAX_FALL_SPEED);
+        assert!(player.pos.y - start_y <= TILE_H);
+    }
+
+    #[test]
+    fn long_frame_runs_bounded_steps() {
+        let stage = test_stage();
+        let mut anims = fallback_anims();
+        let mut prev_anim = AnimId::Idle;
+        let mut player = Player::spawn(vec2(544.0, stage.ground_y));
+        let mut clock = StepClock::new(player.pos);
+
+        let steps = clock.advance(0.5, |clock| {
+            clock.prev_pos = player.pos;
+            step_player(
+                &mut player,
+                &mut anims,
+                &mut prev_anim,
+                &clock.input,
+                &stage,
+                FIXED_DT,
+            );
+            true
+        });
+
+        // 0.5 s unclamped would be 60 steps
+        assert_eq!(steps, (MAX_FRAME_DT / FIXED_DT).round() as usize);
+        assert!(clock.accumulator < FIXED_DT);
+    }
 
-                // Update + draw only the current anim
-                let flip_x = player.facing < 0.0;
-                let a = anim_mut(&mut anims, wanted);
-                a.update(dt);
-                a.draw(player.pos, scale, flip_x);
+    #[test]
+    fn buffered_jump_fires_on_landing() {
+        let mut sim = Sim::new();
+        // Falling just above the ground with no air jumps left, so the press
+        // can only be buffered.
+        sim.player.pos.y = sim.stage.ground_y - 10.0;
+        sim.player.vel.y = 300.0;
+        sim.player.on_ground = false;
+        sim.player.state = MoveState::Jump;
+        sim.player.air_jumps_left = 0;
+
+        assert!(!sim.step(&press()).jumped);
+        let mut landed_at = None;
+        for i in 0..(JUMP_BUFFER_TIME / FIXED_DT) as usize {
+            let events = sim.step(&hold());
+            if events.landed.is_some() {
+                landed_at = Some(i);
+            }
+            if events.jumped {
+                assert!(landed_at.is_some(), "jumped before landing");
+                assert!(sim.player.vel.y < 0.0);
+                return;
             }
         }
+        panic!("buffered jump never fired (landed at {:?})", landed_at);
+    }
 
-        next_frame().await;
+    #[test]
+    fn coyote_jump_after_leaving_the_ground() {
+        let mut sim = Sim::new();
+        sim.step(&InputState::default());
+        // The ground line drops away under the player, as off a ledge.
+        sim.stage.ground_y += 200.0;
+        for _ in 0..3 {
+            sim.step(&InputState::default());
+        }
+        assert!(!sim.player.on_ground);
+        assert!(sim.player.coyote_timer > 0.0);
+
+        assert!(sim.step(&press()).jumped);
+        assert!(sim.player.vel.y < -0.9 * JUMP_SPEED);
+        // a ground jump, so the air jump is still there
+        assert_eq!(sim.player.air_jumps_left, MAX_AIR_JUMPS);
+    }
+
+    #[test]
+    fn late_press_after_coyote_time_is_an_air_jump() {
+        let mut sim = Sim::new();
+        sim.step(&InputState::default());
+        sim.stage.ground_y += 400.0;
+        for _ in 0..(COYOTE_TIME / FIXED_DT) as usize + 2 {
+            sim.step(&InputState::default());
+        }
+        assert!(sim.step(&press()).jumped);
+        assert_eq!(sim.player.air_jumps_left, MAX_AIR_JUMPS - 1);
+    }
+
+    #[test]
+    fn early_release_cuts_the_jump_short() {
+        let peak = |release_after: Option<usize>| {
+            let mut sim = Sim::new();
+            sim.step(&press());
+            let mut top = sim.player.pos.y;
+            for i in 0..120 {
+                let input = match release_after {
+                    Some(n) if i == n => release(),
+                    Some(n) if i > n => InputState::default(),
+                    _ => hold(),
+                };
+                sim.step(&input);
+                top = top.min(sim.player.pos.y);
+            }
+            sim.stage.ground_y - top
+        };
+        let full = peak(None);
+        let short = peak(Some(6));
+        assert!(short < 0.5 * full, "short hop {} vs full {}", short, full);
+    }
+
+    #[test]
+    fn air_jumps_run_out() {
+        let mut sim = Sim::new();
+        assert!(sim.step(&press()).jumped);
+        let mut air_jumps = 0;
+        for _ in 0..MAX_AIR_JUMPS + 1 {
+            for _ in 0..4 {
+                sim.step(&InputState::default());
+            }
+            if sim.step(&press()).jumped {
+                air_jumps += 1;
+            }
+        }
+        assert!(!sim.player.on_ground);
+        assert_eq!(air_jumps, MAX_AIR_JUMPS);
+        assert_eq!(sim.player.air_jumps_left, 0);
+    }
+
+    #[test]
+    fn dash_speed_bleeds_off() {
+        let next = accelerate_x(DASH_SPEED, RUN_SPEED, true, FIXED_DT);
+        assert!(next > MAX_GROUND_SPEED && next < DASH_SPEED);
+        let capped = accelerate_x(MAX_GROUND_SPEED, 2.0 * MAX_GROUND_SPEED, true, FIXED_DT);
+        assert_eq!(capped, MAX_GROUND_SPEED);
+    }
+
+    #[test]
+    fn attract_demo_still_replays() {
+        let rec = Recording::parse(include_str!("../assets/demo.replay")).unwrap();
+        let mut sim = Sim::new();
+        sim.stage.ground_y = rec.ground_y;
+        sim.stage.width = rec.stage_width;
+        sim.player = Player::spawn(rec.start);
+        let mut pb = Playback::new(rec, FIXED_DT).unwrap();
+        while let Some(input) = pb.next_input().cloned() {
+            sim.step(&input);
+            pb.check(sim.player.pos);
+        }
+        assert!(!pb.diverged(), "assets/demo.replay needs re-recording");
     }
 }
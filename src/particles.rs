@@ -0,0 +1,176 @@
+use macroquad::prelude::*;
+
+/// Upper bound on live particles; once full, the oldest slot is reused.
+const MAX_PARTICLES: usize = 300;
+/// px/s² pulling particles down (lighter than the player's gravity).
+const PARTICLE_GRAVITY: f32 = 900.0;
+/// Landings slower than this (px/s) raise no dust.
+const DUST_MIN_IMPACT: f32 = 300.0;
+/// Seed for the particle RNG, restored by `clear` (see `Particles`).
+const SEED: u32 = 0x2545_f491;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Particle {
+    pos: Vec2,
+    vel: Vec2,
+    /// Seconds left; <= 0 means the slot is free.
+    life: f32,
+    max_life: f32,
+    /// Never drawn below the height it spawned at (the ground, for dust).
+    floor: f32,
+    size: f32,
+    color: Color,
+}
+
+/// Cosmetic particles in world space. Nothing in the simulation reads them.
+///
+/// Randomness comes from a small xorshift generator that `clear` reseeds, so
+/// a replayed run spawns the same particles as the recorded one.
+pub struct Particles {
+    pool: Vec<Particle>,
+    /// Next slot to overwrite once the pool is full (oldest first).
+    next: usize,
+    rng: u32,
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Self {
+            pool: Vec::with_capacity(MAX_PARTICLES),
+            next: 0,
+            rng: SEED,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pool.clear();
+        self.next = 0;
+        self.rng = SEED;
+    }
+
+    /// Uniform in `lo..hi`.
+    fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        lo + (hi - lo) * (self.rng as f32 / u32::MAX as f32)
+    }
+
+    fn spawn(&mut self, particle: Particle) {
+        if self.pool.len() < MAX_PARTICLES {
+            self.pool.push(particle);
+        } else {
+            self.pool[self.next] = particle;
+            self.next = (self.next + 1) % MAX_PARTICLES;
+        }
+    }
+
+    /// `count` particles from `pos`, flying at `angle ± spread` (radians,
+    /// 0 = right, -PI/2 = up) with a speed in `speed`.
+    fn burst(&mut self, pos: Vec2, count: usize, angle: f32, spread: f32, speed: (f32, f32)) {
+        for _ in 0..count {
+            let a = angle + self.range(-spread, spread);
+            let v = self.range(speed.0, speed.1);
+            let life = self.range(0.25, 0.5);
+            let size = self.range(6.0, 12.0);
+            let shade = self.range(0.55, 0.8);
+            self.spawn(Particle {
+                pos,
+                vel: vec2(a.cos(), a.sin()) * v,
+                life,
+                max_life: life,
+                floor: pos.y,
+                size,
+                color: Color::new(shade, shade, shade * 0.9, 1.0),
+            });
+        }
+    }
+
+    /// Dust kicked out low to both sides of the feet; more for harder landings.
+    pub fn landing_dust(&mut self, feet: Vec2, impact: f32) {
+        if impact < DUST_MIN_IMPACT {
+            return;
+        }
+        let count = (impact / 100.0) as usize + 2;
+        let speed = (impact * 0.15, impact * 0.35);
+        self.burst(feet, count / 2, -0.3, 0.25, speed);
+        self.burst(feet, count / 2, std::f32::consts::PI + 0.3, 0.25, speed);
+    }
+
+    /// Small puff spreading out from the feet on a jump.
+    pub fn jump_puff(&mut self, feet: Vec2) {
+        self.burst(feet, 6, -std::f32::consts::FRAC_PI_2, 1.3, (60.0, 160.0));
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for p in self.pool.iter_mut().filter(|p| p.life > 0.0) {
+            p.vel.y += PARTICLE_GRAVITY * dt;
+            p.pos += p.vel * dt;
+            if p.pos.y > p.floor {
+                p.pos.y = p.floor;
+                p.vel *= vec2(0.5, 0.0);
+            }
+            p.life -= dt;
+        }
+    }
+
+    /// Squares that shrink and fade out over their lifetime.
+    pub fn draw(&self) {
+        for p in self.pool.iter().filter(|p| p.life > 0.0) {
+            let t = p.life / p.max_life;
+            let size = p.size * (0.5 + 0.5 * t);
+            let color = Color::new(p.color.r, p.color.g, p.color.b, t);
+            draw_rectangle(
+                p.pos.x - size * 0.5,
+                p.pos.y - size * 0.5,
+                size,
+                size,
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle_at(x: f32) -> Particle {
+        Particle {
+            pos: vec2(x, 0.0),
+            vel: Vec2::ZERO,
+            life: 1.0,
+            max_life: 1.0,
+            floor: 0.0,
+            size: 8.0,
+            color: WHITE,
+        }
+    }
+
+    #[test]
+    fn pool_is_capped_and_reuses_the_oldest() {
+        let mut particles = Particles::new();
+        for i in 0..MAX_PARTICLES + 2 {
+            particles.spawn(particle_at(i as f32));
+        }
+        assert_eq!(particles.pool.len(), MAX_PARTICLES);
+        assert_eq!(particles.pool[0].pos.x, MAX_PARTICLES as f32);
+        assert_eq!(particles.pool[1].pos.x, (MAX_PARTICLES + 1) as f32);
+        assert_eq!(particles.pool[2].pos.x, 2.0);
+    }
+
+    #[test]
+    fn clear_replays_the_same_particles() {
+        let run = |particles: &mut Particles| {
+            particles.jump_puff(vec2(100.0, 500.0));
+            particles.landing_dust(vec2(300.0, 500.0), 900.0);
+            particles.update(0.1);
+            particles.pool.clone()
+        };
+        let mut particles = Particles::new();
+        let first = run(&mut particles);
+        particles.clear();
+        assert!(particles.pool.is_empty());
+        assert_eq!(run(&mut particles), first);
+    }
+}
@@ -0,0 +1,115 @@
+use macroquad::prelude::*;
+
+/// Strength of the CRT look, 0 (off) to 1.
+const CRT_INTENSITY: f32 = 0.6;
+
+const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying mediump vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+"#;
+
+/// Barrel curvature, one dark band per target row, a faint flicker and a
+/// vignette. GLSL 100 so it also compiles on WebGL 1.
+const CRT_FRAGMENT: &str = r#"#version 100
+precision mediump float;
+
+varying mediump vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+uniform float time;
+uniform float intensity;
+uniform float rows;
+
+void main() {
+    vec2 centered = uv - 0.5;
+    vec2 curved = uv + centered * dot(centered, centered) * 0.25 * intensity;
+    if (curved.x < 0.0 || curved.x > 1.0 || curved.y < 0.0 || curved.y > 1.0) {
+        gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec3 rgb = texture2D(Texture, curved).rgb;
+    float scan = 1.0 - intensity * 0.35 * (0.5 + 0.5 * cos(curved.y * rows * 6.2831853));
+    float flicker = 1.0 - intensity * 0.02 * sin(time * 50.0);
+    vec2 edge = curved * (1.0 - curved);
+    float vignette = mix(1.0, clamp(pow(edge.x * edge.y * 16.0, 0.25), 0.0, 1.0), intensity);
+    gl_FragColor = vec4(rgb * scan * flicker * vignette, 1.0) * color;
+}
+"#;
+
+/// Post-processing for the world view: the render target is blitted to the
+/// screen through a shader instead of drawn as-is.
+///
+/// Only the persistent CRT/scanline look exists so far. If the shader does
+/// not compile (some WebGL drivers, or a backend without GLSL) the view is
+/// blitted plainly and a warning is logged once at startup.
+pub struct PostFx {
+    crt: Option<Material>,
+}
+
+impl PostFx {
+    pub fn new() -> Self {
+        let crt = load_material(
+            ShaderSource::Glsl {
+                vertex: VERTEX,
+                fragment: CRT_FRAGMENT,
+            },
+            MaterialParams {
+                uniforms: vec![
+                    UniformDesc::new("time", UniformType::Float1),
+                    UniformDesc::new("intensity", UniformType::Float1),
+                    UniformDesc::new("rows", UniformType::Float1),
+                ],
+                ..Default::default()
+            },
+        );
+        match crt {
+            Ok(material) => Self {
+                crt: Some(material),
+            },
+            Err(err) => {
+                warn!("CRT shader failed to build: {:?}; drawing without it", err);
+                Self { crt: None }
+            }
+        }
+    }
+
+    /// Draw `texture` (a render target, so flipped) into `dest`, through the
+    /// CRT shader when `crt` is on and the shader is available.
+    pub fn blit(&self, texture: &Texture2D, dest: Rect, crt: bool) {
+        let material = self.crt.as_ref().filter(|_| crt);
+        if let Some(material) = material {
+            material.set_uniform("time", get_time() as f32);
+            material.set_uniform("intensity", CRT_INTENSITY);
+            material.set_uniform("rows", texture.height());
+            gl_use_material(material);
+        }
+        draw_texture_ex(
+            texture,
+            dest.x,
+            dest.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(dest.size()),
+                flip_y: true, // render targets come out upside down
+                ..Default::default()
+            },
+        );
+        if material.is_some() {
+            gl_use_default_material();
+        }
+    }
+}
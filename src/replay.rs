@@ -0,0 +1,343 @@
+use macroquad::prelude::*;
+
+use crate::MoveState;
+use crate::input::InputState;
+
+/// Where F9 writes a recording; `--replay <path>` plays one back.
+pub const RECORDING_PATH: &str = "recording.replay";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// A checkpoint (player position after that step) is stored this often, so a
+/// replay that drifts reports roughly where it started to go wrong.
+const CHECKPOINT_INTERVAL: usize = 120;
+
+/// Positions closer than this count as matching.
+const CHECK_EPSILON: f32 = 0.01;
+
+/// Longest recording `parse` accepts: an hour at 120 steps per second. Keeps
+/// a corrupt or hostile file from allocating without bound.
+const MAX_STEPS: usize = 60 * 60 * 120;
+
+/// One run's worth of fixed-step input, plus what is needed to start the
+/// simulation in the same place and to verify the result.
+///
+/// Input is stored per fixed step rather than per rendered frame: the
+/// simulation only ever sees step input, so this is what replays exactly
+/// regardless of the frame rate it is played back at.
+///
+/// Text format, one record per line:
+///
+/// ```text
+/// replay 1
+/// fixed_dt 0.008333334
+/// stage <width> <ground_y>
+/// start <x> <y>
+/// in <count> <flags hex> <mode: - I W R>   (run-length encoded steps)
+/// check <step> <x> <y>
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub fixed_dt: f32,
+    pub stage_width: f32,
+    pub ground_y: f32,
+    pub start: Vec2,
+    steps: Vec<InputState>,
+    /// (steps taken, player position) -- the last one is the final position.
+    checks: Vec<(usize, Vec2)>,
+}
+
+/// Only the gameplay fields are stored; menu edges never reach the simulation.
+fn encode(input: &InputState) -> (u32, char) {
+    let bits = [
+        input.left,
+        input.right,
+        input.down,
+        input.sprint,
+        input.jump_held,
+        input.jump_pressed,
+        input.jump_released,
+        input.dash_pressed,
+        input.mode_cycle_pressed,
+    ];
+    let flags = bits
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (bit, on)| acc | (*on as u32) << bit);
+    let mode = match input.mode_pressed {
+        None => '-',
+        Some(MoveState::Walk) => 'W',
+        Some(MoveState::Run) => 'R',
+        Some(_) => 'I',
+    };
+    (flags, mode)
+}
+
+fn decode(flags: u32, mode: char) -> Option<InputState> {
+    let bit = |n: u32| flags & 1 << n != 0;
+    Some(InputState {
+        left: bit(0),
+        right: bit(1),
+        down: bit(2),
+        sprint: bit(3),
+        jump_held: bit(4),
+        jump_pressed: bit(5),
+        jump_released: bit(6),
+        dash_pressed: bit(7),
+        mode_cycle_pressed: bit(8),
+        mode_pressed: match mode {
+            '-' => None,
+            'I' => Some(MoveState::Idle),
+            'W' => Some(MoveState::Walk),
+            'R' => Some(MoveState::Run),
+            _ => return None,
+        },
+        ..Default::default()
+    })
+}
+
+impl Recording {
+    pub fn new(fixed_dt: f32, stage_width: f32, ground_y: f32, start: Vec2) -> Self {
+        Self {
+            fixed_dt,
+            stage_width,
+            ground_y,
+            start,
+            ..Default::default()
+        }
+    }
+
+    /// Append one step's input and the position it produced.
+    pub fn record(&mut self, input: &InputState, pos: Vec2) {
+        self.steps.push(input.clone());
+        if self.steps.len().is_multiple_of(CHECKPOINT_INTERVAL) {
+            self.checks.push((self.steps.len(), pos));
+        }
+    }
+
+    /// Store the final position (unless the last step was already a checkpoint).
+    pub fn finish(&mut self, pos: Vec2) {
+        if self.checks.last().map(|(step, _)| *step) != Some(self.steps.len()) {
+            self.checks.push((self.steps.len(), pos));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn seconds(&self) -> f32 {
+        self.steps.len() as f32 * self.fixed_dt
+    }
+
+    pub fn to_file_string(&self) -> String {
+        let mut out = format!(
+            "replay {}\nfixed_dt {}\nstage {} {}\nstart {} {}\n",
+            FORMAT_VERSION,
+            self.fixed_dt,
+            self.stage_width,
+            self.ground_y,
+            self.start.x,
+            self.start.y
+        );
+        let mut i = 0;
+        while i < self.steps.len() {
+            let count = self.steps[i..]
+                .iter()
+                .take_while(|s| **s == self.steps[i])
+                .count();
+            let (flags, mode) = encode(&self.steps[i]);
+            out.push_str(&format!("in {} {:x} {}\n", count, flags, mode));
+            i += count;
+        }
+        for (step, pos) in &self.checks {
+            out.push_str(&format!("check {} {} {}\n", step, pos.x, pos.y));
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rec = Self::default();
+        let mut version = None;
+        for (n, line) in text.lines().enumerate() {
+            let err = |what: &str| format!("line {}: {}", n + 1, what);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let num = |i: usize| -> Result<f32, String> {
+                words
+                    .get(i)
+                    .and_then(|w| w.parse().ok())
+                    .ok_or_else(|| err("expected a number"))
+            };
+            let int = |i: usize| -> Result<usize, String> {
+                words
+                    .get(i)
+                    .and_then(|w| w.parse().ok())
+                    .ok_or_else(|| err("expected a whole number"))
+            };
+            match words.first().copied() {
+                None => {}
+                Some("replay") => version = Some(num(1)? as u32),
+                Some("fixed_dt") => rec.fixed_dt = num(1)?,
+                Some("stage") => (rec.stage_width, rec.ground_y) = (num(1)?, num(2)?),
+                Some("start") => rec.start = vec2(num(1)?, num(2)?),
+                Some("in") => {
+                    let count = int(1)?;
+                    if count > MAX_STEPS - rec.steps.len() {
+                        return Err(err(&format!("more than {} steps", MAX_STEPS)));
+                    }
+                    let input = words
+                        .get(2)
+                        .and_then(|w| u32::from_str_radix(w, 16).ok())
+                        .zip(words.get(3).and_then(|w| w.chars().next()))
+                        .and_then(|(flags, mode)| decode(flags, mode))
+                        .ok_or_else(|| err("bad input record"))?;
+                    rec.steps.extend(std::iter::repeat_n(input, count));
+                }
+                Some("check") => rec.checks.push((int(1)?, vec2(num(2)?, num(3)?))),
+                Some(other) => return Err(err(&format!("unknown record {:?}", other))),
+            }
+        }
+        match version {
+            Some(FORMAT_VERSION) => Ok(rec),
+            Some(v) => Err(format!("unsupported replay version {}", v)),
+            None => Err("not a replay file".to_string()),
+        }
+    }
+
+    /// Load with `load_string` (so it works from assets on the web build too).
+    pub async fn load(path: &str) -> Result<Self, String> {
+        let text = load_string(path).await.map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_file_string()).map_err(|e| e.to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self, _path: &str) -> Result<(), String> {
+        Err("saving recordings is not supported on the web build".to_string())
+    }
+}
+
+/// Feeds a recording back one fixed step at a time and compares checkpoints.
+pub struct Playback {
+    recording: Recording,
+    step: usize,
+    next_check: usize,
+    diverged: bool,
+}
+
+impl Playback {
+    /// Refuses recordings made with a different fixed timestep, since the
+    /// same inputs would then not reproduce the same run.
+    pub fn new(recording: Recording, fixed_dt: f32) -> Result<Self, String> {
+        if recording.fixed_dt != fixed_dt {
+            return Err(format!(
+                "recorded with fixed_dt {} but this build uses {}",
+                recording.fixed_dt, fixed_dt
+            ));
+        }
+        Ok(Self {
+            recording,
+            step: 0,
+            next_check: 0,
+            diverged: false,
+        })
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step >= self.recording.steps.len()
+    }
+
+    pub fn diverged(&self) -> bool {
+        self.diverged
+    }
+
+    /// Input for the next step, or `None` once the recording has run out.
+    pub fn next_input(&mut self) -> Option<&InputState> {
+        let input = self.recording.steps.get(self.step)?;
+        self.step += 1;
+        Some(input)
+    }
+
+    /// Call after simulating the step returned by `next_input`. Warns (once)
+    /// with the step number if the run no longer matches the recording.
+    pub fn check(&mut self, pos: Vec2) {
+        let Some(&(step, expected)) = self.recording.checks.get(self.next_check) else {
+            return;
+        };
+        if step != self.step {
+            return;
+        }
+        self.next_check += 1;
+        if !self.diverged && pos.distance(expected) > CHECK_EPSILON {
+            self.diverged = true;
+            warn!(
+                "replay diverged at step {}: player at ({:.2}, {:.2}), recorded ({:.2}, {:.2})",
+                step, pos.x, pos.y, expected.x, expected.y
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    fn sample() -> Recording {
+        let mut rec = Recording::new(DT, 1280.0, 348.0, vec2(544.0, 348.0));
+        let walk = InputState {
+            right: true,
+            mode_pressed: Some(MoveState::Walk),
+            ..Default::default()
+        };
+        let jump = InputState {
+            right: true,
+            jump_held: true,
+            jump_pressed: true,
+            ..Default::default()
+        };
+        for step in 0..300 {
+            let input = if step % 50 == 0 { &jump } else { &walk };
+            rec.record(input, vec2(544.0 + step as f32, 348.0));
+        }
+        rec.finish(vec2(844.0, 348.0));
+        rec
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let rec = sample();
+        let text = rec.to_file_string();
+        let parsed = Recording::parse(&text).unwrap();
+        assert_eq!(parsed.len(), rec.len());
+        assert_eq!(parsed.steps, rec.steps);
+        assert_eq!(parsed.checks, rec.checks);
+        assert_eq!(parsed.to_file_string(), text);
+    }
+
+    #[test]
+    fn rejects_oversized_counts() {
+        let text = format!("replay 1\nfixed_dt {}\nin {} 0 -\n", DT, MAX_STEPS + 1);
+        assert!(Recording::parse(&text).is_err());
+        assert!(Recording::parse("replay 1\nin -5 0 -\n").is_err());
+    }
+
+    #[test]
+    fn playback_refuses_other_fixed_dt() {
+        assert!(Playback::new(sample(), 1.0 / 60.0).is_err());
+        assert!(Playback::new(sample(), DT).is_ok());
+    }
+}
@@ -0,0 +1,214 @@
+use macroquad::prelude::*;
+
+/// Settings file, read at startup and written back by the settings screen.
+/// Lives next to `bindings.toml` (paths are relative to the working dir).
+pub const SETTINGS_PATH: &str = "settings.toml";
+
+/// Player-facing options. There is no audio yet, so no volume either.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub fullscreen: bool,
+    pub show_fps: bool,
+    /// Scanlines and curvature on the world view (see `PostFx`).
+    pub crt: bool,
+}
+
+/// Keeps `Settings::fullscreen` honest when the OS or window manager takes
+/// the window out of fullscreen behind the game's back; macroquad can't say
+/// whether the window is fullscreen.
+///
+/// Rule: while the flag is set, a resize that lands exactly on the window
+/// size seen at startup means fullscreen was left, so the flag is cleared.
+/// Without an actual resize nothing changes, so a display whose fullscreen
+/// size equals the startup size keeps the flag as toggled. A window
+/// fullscreened from outside the game is not detected.
+pub struct FullscreenWatch {
+    windowed: Vec2,
+    last: Vec2,
+}
+
+impl FullscreenWatch {
+    /// Create before applying a saved fullscreen flag, so the size seen is
+    /// the windowed one.
+    pub fn new() -> Self {
+        let size = vec2(screen_width(), screen_height());
+        Self {
+            windowed: size,
+            last: size,
+        }
+    }
+
+    /// Call once per frame; returns true if it cleared the flag.
+    pub fn update(&mut self, settings: &mut Settings) -> bool {
+        let size = vec2(screen_width(), screen_height());
+        let left = settings.fullscreen && size != self.last && size == self.windowed;
+        if left {
+            settings.fullscreen = false;
+        }
+        self.last = size;
+        left
+    }
+}
+
+/// Rows of the settings screen, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Fullscreen,
+    ShowFps,
+    Crt,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 3] = [Setting::Fullscreen, Setting::ShowFps, Setting::Crt];
+
+    /// Key used in the settings file.
+    fn config_name(self) -> &'static str {
+        match self {
+            Setting::Fullscreen => "fullscreen",
+            Setting::ShowFps => "show_fps",
+            Setting::Crt => "crt",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Setting::Fullscreen => "Fullscreen",
+            Setting::ShowFps => "Show FPS",
+            Setting::Crt => "CRT filter",
+        }
+    }
+}
+
+impl Settings {
+    /// Load from `SETTINGS_PATH`, falling back to defaults when missing.
+    pub async fn load() -> Self {
+        match load_string(SETTINGS_PATH).await {
+            Ok(text) => Self::parse(&text),
+            Err(_) => {
+                info!("no {} found, using default settings", SETTINGS_PATH);
+                Self::default()
+            }
+        }
+    }
+
+    /// Parse `name = true|false` lines on top of the defaults. Anything
+    /// unrecognised is skipped with a warning and keeps its default.
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                warn!("{}: ignoring malformed line {:?}", SETTINGS_PATH, line);
+                continue;
+            };
+            let name = name.trim();
+            let Some(setting) = Setting::ALL
+                .iter()
+                .copied()
+                .find(|s| s.config_name() == name)
+            else {
+                warn!("{}: unknown setting {:?}", SETTINGS_PATH, name);
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<bool>() else {
+                warn!("{}: {} must be true or false", SETTINGS_PATH, name);
+                continue;
+            };
+            *settings.flag_mut(setting) = value;
+        }
+        settings
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let mut out = String::from("# Settings (name = true|false)\n");
+        for setting in Setting::ALL {
+            out.push_str(&format!(
+                "{} = {}\n",
+                setting.config_name(),
+                self.flag(setting)
+            ));
+        }
+        out
+    }
+
+    /// Write back to `SETTINGS_PATH`. On the web build there is no writable
+    /// file system, so changes only last for the session.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> Result<(), String> {
+        std::fs::write(SETTINGS_PATH, self.to_config_string()).map_err(|e| e.to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) -> Result<(), String> {
+        Err("saving settings is not supported on the web build".to_string())
+    }
+
+    pub fn flag(&self, setting: Setting) -> bool {
+        match setting {
+            Setting::Fullscreen => self.fullscreen,
+            Setting::ShowFps => self.show_fps,
+            Setting::Crt => self.crt,
+        }
+    }
+
+    fn flag_mut(&mut self, setting: Setting) -> &mut bool {
+        match setting {
+            Setting::Fullscreen => &mut self.fullscreen,
+            Setting::ShowFps => &mut self.show_fps,
+            Setting::Crt => &mut self.crt,
+        }
+    }
+
+    /// Flip `setting` and apply it right away.
+    pub fn toggle(&mut self, setting: Setting) {
+        let flag = self.flag_mut(setting);
+        *flag = !*flag;
+        self.apply(setting);
+    }
+
+    /// Push a setting to macroquad. Settings that are only read while
+    /// drawing (show FPS, CRT filter) need nothing here.
+    pub fn apply(&self, setting: Setting) {
+        match setting {
+            Setting::Fullscreen => set_fullscreen(self.fullscreen),
+            Setting::ShowFps | Setting::Crt => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_or_bad_lines_keep_defaults() {
+        let settings = Settings::parse(
+            "# comment\n\nshow_fps = true\nfullscreen\nvolume = 3\nfullscreen = yes\n",
+        );
+        assert_eq!(
+            settings,
+            Settings {
+                show_fps: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(Settings::parse(""), Settings::default());
+    }
+
+    #[test]
+    fn config_string_round_trips() {
+        let settings = Settings {
+            fullscreen: true,
+            show_fps: true,
+            crt: true,
+        };
+        assert_eq!(Settings::parse(&settings.to_config_string()), settings);
+        assert_eq!(
+            Settings::parse(&Settings::default().to_config_string()),
+            Settings::default()
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use macroquad::prelude::*;
+
+/// On-screen buttons, laid out from the current screen size every frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Right,
+    Crouch,
+    Jump,
+    Dash,
+    Mode,
+}
+
+impl Button {
+    const ALL: [Button; 6] = [
+        Button::Left,
+        Button::Right,
+        Button::Crouch,
+        Button::Jump,
+        Button::Dash,
+        Button::Mode,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Button::Left => "<",
+            Button::Right => ">",
+            Button::Crouch => "v",
+            Button::Jump => "JUMP",
+            Button::Dash => "DASH",
+            Button::Mode => "MODE",
+        }
+    }
+}
+
+/// One bool per button.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ButtonSet([bool; Button::ALL.len()]);
+
+impl ButtonSet {
+    pub fn get(&self, button: Button) -> bool {
+        self.0[button as usize]
+    }
+
+    fn set(&mut self, button: Button) {
+        self.0[button as usize] = true;
+    }
+}
+
+/// Touch state for one frame, in terms of buttons.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TouchFrame {
+    pub held: ButtonSet,
+    pub pressed: ButtonSet,
+    pub released: ButtonSet,
+    /// A new touch started anywhere (menus treat this as Confirm).
+    pub tapped: bool,
+}
+
+/// Virtual d-pad (bottom-left) and action buttons (bottom-right).
+/// Each finger is hit-tested independently, so holding a direction while
+/// tapping jump with a second finger works.
+#[derive(Default)]
+pub struct TouchControls {
+    seen_touch: bool,
+    prev_held: ButtonSet,
+}
+
+impl TouchControls {
+    /// Buttons are shown once a touch has been seen, and always on the web build
+    /// (where a phone has no keyboard to fall back on).
+    pub fn visible(&self) -> bool {
+        self.seen_touch || cfg!(target_arch = "wasm32")
+    }
+
+    fn layout() -> [(Button, Rect); Button::ALL.len()] {
+        let (w, h) = (screen_width(), screen_height());
+        let s = w.min(h) * 0.14;
+        let m = s * 0.3;
+        let bottom = h - m - s;
+        [
+            (Button::Left, Rect::new(m, bottom, s, s)),
+            (Button::Crouch, Rect::new(m * 2.0 + s, bottom, s, s)),
+            (Button::Right, Rect::new(m * 3.0 + s * 2.0, bottom, s, s)),
+            (
+                Button::Jump,
+                Rect::new(w - m - s * 1.4, bottom - s * 0.4, s * 1.4, s * 1.4),
+            ),
+            (Button::Dash, Rect::new(w - m * 2.0 - s * 2.4, bottom, s, s)),
+            (
+                Button::Mode,
+                Rect::new(w - m * 2.0 - s * 2.4, bottom - m - s, s, s),
+            ),
+        ]
+    }
+
+    pub fn update(&mut self) -> TouchFrame {
+        let mut frame = TouchFrame::default();
+        let dpi = macroquad::miniquad::window::dpi_scale();
+        let layout = Self::layout();
+
+        for touch in touches() {
+            self.seen_touch = true;
+            let pos = touch.position / dpi;
+            let started = touch.phase == TouchPhase::Started;
+            let ended = touch.phase == TouchPhase::Ended;
+            let active = !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled);
+            frame.tapped |= started;
+            for (button, rect) in layout {
+                if rect.contains(pos) {
+                    if active {
+                        frame.held.set(button);
+                    }
+                    // macroquad keeps only the last phase of a touch per frame, so
+                    // a tap that starts and ends within one frame arrives as just
+                    // Ended; count it if the button wasn't already held.
+                    if started || (ended && !self.prev_held.get(button)) {
+                        frame.pressed.set(button);
+                        frame.tapped = true;
+                    }
+                }
+            }
+        }
+
+        for button in Button::ALL {
+            let (was, is) = (self.prev_held.get(button), frame.held.get(button));
+            if is && !was {
+                frame.pressed.set(button);
+            }
+            if was && !is {
+                frame.released.set(button);
+            }
+        }
+        self.prev_held = frame.held;
+        frame
+    }
+
+    pub fn draw(&self, frame: &TouchFrame) {
+        if !self.visible() {
+            return;
+        }
+        for (button, rect) in Self::layout() {
+            let alpha = if frame.held.get(button) { 0.6 } else { 0.25 };
+            draw_rectangle(
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                Color::new(1.0, 1.0, 1.0, alpha),
+            );
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, WHITE);
+            let font_size = rect.h * 0.3;
+            let dims = measure_text(button.label(), None, font_size as u16, 1.0);
+            draw_text(
+                button.label(),
+                rect.x + (rect.w - dims.width) * 0.5,
+                rect.y + (rect.h + dims.offset_y) * 0.5,
+                font_size,
+                WHITE,
+            );
+        }
+    }
+}